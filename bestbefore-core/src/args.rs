@@ -0,0 +1,597 @@
+use crate::date::{BestBeforeDate, DateError};
+use proc_macro2::Span;
+use std::ops::Range;
+use syn::{parse::Parse, parse::ParseStream, LitStr, Token};
+
+/// A stage of the escalation ladder, in increasing order of severity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    /// Documented in rustdoc only, no compiler diagnostic.
+    Note,
+    /// Deprecation warning wherever the item is used.
+    Warn,
+    /// Deprecation warning locally, compile error when running under CI.
+    DenyInCi,
+    /// Compile error.
+    Error,
+}
+
+impl Stage {
+    const ALL: [Stage; 4] = [Stage::Note, Stage::Warn, Stage::DenyInCi, Stage::Error];
+
+    /// The key used for this stage in `stages(...)`.
+    pub fn key(self) -> &'static str {
+        match self {
+            Stage::Note => "note",
+            Stage::Warn => "warn",
+            Stage::DenyInCi => "deny_in_ci",
+            Stage::Error => "error",
+        }
+    }
+
+    /// How the date of this stage is referred to in diagnostics.
+    fn date_label(self) -> &'static str {
+        match self {
+            Stage::Note => "note date",
+            Stage::Warn => "warning date",
+            Stage::DenyInCi => "deny-in-CI date",
+            Stage::Error => "expiration date",
+        }
+    }
+
+    /// The stage with the given key.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Stage::ALL.into_iter().find(|stage| name == stage.key())
+    }
+
+    fn from_key(key: &syn::Ident) -> syn::Result<Self> {
+        Stage::from_name(&key.to_string()).ok_or_else(|| {
+            syn::Error::new(
+                key.span(),
+                format!(
+                    "Unknown stage '{}', expected one of 'note', 'warn', 'deny_in_ci' or 'error'",
+                    key
+                ),
+            )
+        })
+    }
+}
+
+/// Where the warning of the `warn` and `deny_in_ci` stages is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarnAt {
+    /// Wherever the item is used, through `#[deprecated]`.
+    Use,
+    /// At the annotated item itself, even if it is never used.
+    Definition,
+    /// Both of the above.
+    Both,
+}
+
+impl WarnAt {
+    fn parse(lit: &LitStr) -> syn::Result<Self> {
+        match lit.value().as_str() {
+            "use" => Ok(WarnAt::Use),
+            "definition" => Ok(WarnAt::Definition),
+            "both" => Ok(WarnAt::Both),
+            other => Err(syn::Error::new(
+                lit.span(),
+                format!(
+                    "Invalid warn_at: '{}', expected 'use', 'definition' or 'both'",
+                    other
+                ),
+            )),
+        }
+    }
+
+    pub fn at_use(self) -> bool {
+        matches!(self, WarnAt::Use | WarnAt::Both)
+    }
+
+    pub fn at_definition(self) -> bool {
+        matches!(self, WarnAt::Definition | WarnAt::Both)
+    }
+}
+
+/// A date argument together with the span of the literal it was parsed from.
+#[derive(Clone, Copy)]
+pub struct SpannedDate {
+    pub value: BestBeforeDate,
+    pub span: Span,
+}
+
+impl SpannedDate {
+    fn parse(lit: &LitStr) -> syn::Result<Self> {
+        let text = lit.value();
+        let value = BestBeforeDate::parse(&text).map_err(|err| {
+            match lit_subspan(lit, err.range.clone()) {
+                Some(span) => syn::Error::new(span, err.message),
+                None => syn::Error::new(lit.span(), located_message(&text, &err)),
+            }
+        })?;
+        Ok(SpannedDate {
+            value,
+            span: lit.span(),
+        })
+    }
+}
+
+/// A stage of the ladder together with the date it starts after.
+#[derive(Clone, Copy)]
+pub struct StageDate {
+    pub stage: Stage,
+    pub date: SpannedDate,
+}
+
+/// Narrows the span of `lit` to the byte `range` of its value.
+///
+/// Only nightly compilers can point into a literal; stable ones return `None`, as do escaped or
+/// raw strings, whose source text does not map one-to-one onto the value.
+fn lit_subspan(lit: &LitStr, range: Range<usize>) -> Option<Span> {
+    let token = lit.token();
+    let source = token.to_string();
+    let value = lit.value();
+    if source.len() == value.len() + 2 && source[1..source.len() - 1] == value {
+        token.subspan(range.start + 1..range.end + 1)
+    } else {
+        None
+    }
+}
+
+/// The message of `err` for a diagnostic on the whole literal `text`, naming the column and the
+/// characters at fault when they are only part of it.
+fn located_message(text: &str, err: &DateError) -> String {
+    if err.range == (0..text.len()) {
+        return err.message.clone();
+    }
+    format!(
+        "{} (at column {} of \"{}\": '{}')",
+        err.message,
+        text[..err.range.start].chars().count() + 1,
+        text,
+        &text[err.range.clone()]
+    )
+}
+
+pub struct BestBeforeArgs {
+    /// The configured stages, ordered by severity and with increasing dates, except that a
+    /// warning and an expiry may share one.
+    pub stages: Vec<StageDate>,
+    /// Note attached to the deprecation warning.
+    pub warn_message: Option<String>,
+    /// Text of the compile error once the code has expired.
+    pub expire_message: Option<String>,
+    /// Where warnings are reported.
+    pub warn_at: WarnAt,
+    /// The team or person responsible for the code.
+    pub owner: Option<String>,
+    /// Issue tracker reference, checked and linked according to the project configuration.
+    pub ticket: Option<LitStr>,
+    /// The code to use instead, referenced by the expansion so it is checked by the compiler.
+    pub replacement: Option<syn::Path>,
+    /// Stable identifier of the annotation, for example to snooze it in the configuration.
+    pub id: Option<String>,
+    /// Where the arguments start, for errors about the annotation as a whole.
+    pub span: Span,
+}
+
+impl BestBeforeArgs {
+    /// The most severe stage whose date has passed on `today`.
+    pub fn active_stage(&self, today: chrono::NaiveDate) -> Option<StageDate> {
+        self.stages
+            .iter()
+            .rev()
+            .find(|stage| stage.date.value.is_past(today))
+            .copied()
+    }
+}
+
+impl Parse for BestBeforeArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        BestBeforeArgs::parse_until_body(input, false)
+    }
+}
+
+impl BestBeforeArgs {
+    /// Parses the arguments. With `before_body`, parsing stops at the first argument that is not
+    /// a parameter, which is left in `input` as the code the arguments apply to.
+    fn parse_until_body(input: ParseStream, before_body: bool) -> syn::Result<Self> {
+        let span = input.span();
+        let mut stages: Vec<StageDate> = Vec::new();
+        let mut message: Option<String> = None;
+        let mut warn_message = None;
+        let mut expire_message = None;
+        let mut warn_at = None;
+        let mut owner = None;
+        let mut ticket = None;
+        let mut replacement = None;
+        let mut id = None;
+
+        if input.is_empty() {
+            return Err(syn::Error::new(
+                input.span(),
+                "Missing parameters. Expected either warning date or expires parameter",
+            ));
+        }
+
+        if input.peek(LitStr) {
+            let date_lit: LitStr = input.parse()?;
+            stages.push(StageDate {
+                stage: Stage::Warn,
+                date: SpannedDate::parse(&date_lit)?,
+            });
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        while !input.is_empty() {
+            if before_body && !starts_parameter(input) {
+                break;
+            }
+            let name: syn::Ident = input.parse()?;
+
+            if name == "message" && input.peek(syn::token::Paren) {
+                let content;
+                syn::parenthesized!(content in input);
+                while !content.is_empty() {
+                    let kind: syn::Ident = content.parse()?;
+                    content.parse::<Token![=]>()?;
+                    let msg_lit = content.parse::<LitStr>()?;
+                    if kind == "warn" {
+                        set_once(&mut warn_message, msg_lit.value(), &kind)?;
+                    } else if kind == "expire" {
+                        set_once(&mut expire_message, msg_lit.value(), &kind)?;
+                    } else {
+                        return Err(syn::Error::new(
+                            kind.span(),
+                            format!(
+                                "Unknown message kind '{}', expected 'warn' or 'expire'",
+                                kind
+                            ),
+                        ));
+                    }
+                    if !content.is_empty() {
+                        content.parse::<Token![,]>()?;
+                    }
+                }
+            } else if name == "stages" {
+                let content;
+                syn::parenthesized!(content in input);
+                let mut previous: Option<Stage> = None;
+                while !content.is_empty() {
+                    let key: syn::Ident = content.parse()?;
+                    let stage = Stage::from_key(&key)?;
+                    content.parse::<Token![=]>()?;
+                    let date_lit = content.parse::<LitStr>()?;
+                    if previous.is_some_and(|previous| previous >= stage) {
+                        return Err(syn::Error::new(
+                            key.span(),
+                            "Stages must be listed in the order note, warn, deny_in_ci, error",
+                        ));
+                    }
+                    previous = Some(stage);
+                    add_stage(&mut stages, stage, SpannedDate::parse(&date_lit)?, &key)?;
+                    if !content.is_empty() {
+                        content.parse::<Token![,]>()?;
+                    }
+                }
+            } else {
+                input.parse::<Token![=]>()?;
+
+                if name == "expires" {
+                    let date_lit = input.parse::<LitStr>()?;
+                    add_stage(
+                        &mut stages,
+                        Stage::Error,
+                        SpannedDate::parse(&date_lit)?,
+                        &name,
+                    )?;
+                } else if name == "message" {
+                    let msg_lit = input.parse::<LitStr>()?;
+                    set_once(&mut message, msg_lit.value(), &name)?;
+                } else if name == "warn_message" {
+                    let msg_lit = input.parse::<LitStr>()?;
+                    set_once(&mut warn_message, msg_lit.value(), &name)?;
+                } else if name == "expire_message" {
+                    let msg_lit = input.parse::<LitStr>()?;
+                    set_once(&mut expire_message, msg_lit.value(), &name)?;
+                } else if name == "warn_at" {
+                    let site_lit = input.parse::<LitStr>()?;
+                    set_once(&mut warn_at, WarnAt::parse(&site_lit)?, &name)?;
+                } else if name == "owner" {
+                    let owner_lit = input.parse::<LitStr>()?;
+                    if owner_lit.value().trim().is_empty() {
+                        return Err(syn::Error::new(owner_lit.span(), "owner must not be empty"));
+                    }
+                    set_once(&mut owner, owner_lit.value(), &name)?;
+                } else if name == "ticket" {
+                    let ticket_lit = input.parse::<LitStr>()?;
+                    set_once(&mut ticket, ticket_lit, &name)?;
+                } else if name == "id" {
+                    let id_lit = input.parse::<LitStr>()?;
+                    if id_lit.value().trim().is_empty() {
+                        return Err(syn::Error::new(id_lit.span(), "id must not be empty"));
+                    }
+                    set_once(&mut id, id_lit.value(), &name)?;
+                } else if name == "replacement" {
+                    let path = input.parse::<syn::Path>()?;
+                    set_once(&mut replacement, path, &name)?;
+                } else {
+                    return Err(unknown_parameter(&name));
+                }
+            }
+
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        // Only specifying `expires` is fine: such code goes straight to the error stage.
+        if stages.is_empty() {
+            return Err(syn::Error::new(
+                input.span(),
+                "Missing parameters. You must provide either a warning date, an expires parameter or stages",
+            ));
+        }
+
+        stages.sort_by_key(|stage| stage.stage);
+        for pair in stages.windows(2) {
+            let (earlier, later) = (pair[0], pair[1]);
+            // Code may expire in the period it starts warning in, in which case the error wins.
+            let same_period_allowed = earlier.stage == Stage::Warn && later.stage == Stage::Error;
+            if later.date.value < earlier.date.value
+                || (later.date.value == earlier.date.value && !same_period_allowed)
+            {
+                return Err(syn::Error::new(
+                    later.date.span,
+                    format!(
+                        "Invalid date: {} ({}) must be after {} ({}); \
+                         move it past {} or bring the {} forward",
+                        later.stage.date_label(),
+                        later.date.value,
+                        earlier.stage.date_label(),
+                        earlier.date.value,
+                        earlier.date.value,
+                        earlier.stage.date_label()
+                    ),
+                ));
+            }
+        }
+
+        Ok(BestBeforeArgs {
+            stages,
+            // A plain `message` applies to both stages unless a specific one is given.
+            warn_message: warn_message.or_else(|| message.clone()),
+            expire_message: expire_message.or(message),
+            warn_at: warn_at.unwrap_or(WarnAt::Use),
+            owner,
+            ticket,
+            replacement,
+            id,
+            span,
+        })
+    }
+}
+
+/// The arguments of `bestbefore_block!`: the usual parameters followed by a block or expression.
+pub struct BlockArgs {
+    pub args: BestBeforeArgs,
+    pub body: syn::Expr,
+}
+
+impl Parse for BlockArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let args = BestBeforeArgs::parse_until_body(input, true)?;
+        if input.is_empty() {
+            return Err(syn::Error::new(
+                input.span(),
+                "Missing code. Expected a block or expression after the parameters",
+            ));
+        }
+        let body = input.parse()?;
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+        if !input.is_empty() {
+            return Err(input.error(
+                "Unexpected tokens after the code. The block or expression must be the last argument",
+            ));
+        }
+        Ok(BlockArgs { args, body })
+    }
+}
+
+/// Returns `true` if `input` continues with a named parameter rather than with code.
+///
+/// Anything of the form `name = ...`, `message(...)` or `stages(...)` is taken as a parameter, so
+/// misspelled parameter names are still reported as such.
+fn starts_parameter(input: ParseStream) -> bool {
+    let fork = input.fork();
+    let Ok(name) = fork.parse::<syn::Ident>() else {
+        return false;
+    };
+    if fork.peek(Token![=]) && !fork.peek(Token![==]) {
+        return true;
+    }
+    fork.peek(syn::token::Paren) && (name == "message" || name == "stages")
+}
+
+const PARAMETERS: &[&str] = &[
+    "expires",
+    "message",
+    "warn_message",
+    "expire_message",
+    "stages",
+    "warn_at",
+    "owner",
+    "ticket",
+    "replacement",
+    "id",
+];
+
+/// Adds a stage, rejecting a stage that was already configured by another parameter.
+fn add_stage(
+    stages: &mut Vec<StageDate>,
+    stage: Stage,
+    date: SpannedDate,
+    name: &syn::Ident,
+) -> syn::Result<()> {
+    if stages.iter().any(|existing| existing.stage == stage) {
+        return Err(syn::Error::new(
+            name.span(),
+            format!(
+                "Duplicate {}: the '{}' stage is already configured",
+                stage.date_label(),
+                stage.key()
+            ),
+        ));
+    }
+    stages.push(StageDate { stage, date });
+    Ok(())
+}
+
+/// Stores `value` in `slot`, rejecting a parameter that was already given.
+fn set_once<T>(slot: &mut Option<T>, value: T, name: &syn::Ident) -> syn::Result<()> {
+    if slot.is_some() {
+        return Err(syn::Error::new(
+            name.span(),
+            format!("Duplicate parameter '{}'; remove one of them", name),
+        ));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+fn unknown_parameter(ident: &syn::Ident) -> syn::Error {
+    let name = ident.to_string();
+    let expected = PARAMETERS
+        .iter()
+        .map(|parameter| format!("'{}'", parameter))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut message = format!("Unknown parameter '{}', expected one of {}", name, expected);
+    if let Some(closest) = PARAMETERS
+        .iter()
+        .filter(|parameter| edit_distance(&name, parameter) <= 2)
+        .min_by_key(|parameter| edit_distance(&name, parameter))
+    {
+        message = format!("{}; did you mean '{}'?", message, closest);
+    }
+    syn::Error::new(ident.span(), message)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(previous + 1).min(row[j] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> syn::Result<BestBeforeArgs> {
+        syn::parse_str(input)
+    }
+
+    #[test]
+    fn warning_and_expiry_may_share_a_date() {
+        let args = parse(r#""06.2025", expires = "06.2025""#).unwrap();
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 7, 1).unwrap();
+        assert_eq!(
+            args.active_stage(today).map(|stage| stage.stage),
+            Some(Stage::Error)
+        );
+    }
+
+    #[test]
+    fn other_stages_must_not_share_a_date() {
+        let err = parse(r#"stages(note = "06.2025", warn = "06.2025")"#)
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("Invalid date"), "{}", err);
+        assert!(parse(r#"stages(warn = "06.2025", deny_in_ci = "06.2025")"#).is_err());
+    }
+
+    #[test]
+    fn expiry_must_not_precede_the_warning() {
+        assert!(parse(r#""06.2025", expires = "05.2025""#).is_err());
+    }
+
+    /// Asserts that parsing `input` fails with `message`, which is followed by the column of the
+    /// error where the span cannot point into the literal.
+    fn assert_date_error(input: &str, message: &str) {
+        let err = parse(input).err().unwrap().to_string();
+        assert!(err.starts_with(message), "{}", err);
+    }
+
+    #[test]
+    fn malformed_dates() {
+        assert_date_error(
+            r#""2023-02-29""#,
+            "29 is not a day of 2023-02; did you mean 2023-02-28?",
+        );
+        assert_date_error(
+            r#""06.2025", expires = "soon""#,
+            "Invalid date format: 'soon'; ",
+        );
+        assert_date_error(
+            r#"stages(warn = "13.2025")"#,
+            "13 is not a month; did you mean 12.2025?",
+        );
+    }
+
+    #[test]
+    fn malformed_quarters_and_weeks() {
+        assert_date_error(r#""2024-Q5""#, "5 is not a quarter; did you mean 2024-Q4?");
+        assert_date_error(
+            r#""06.2025", expires = "2021-W53""#,
+            "2021 has no week 53 (weeks run from 1 to 52); did you mean 2021-W52?",
+        );
+    }
+
+    #[test]
+    fn quarters_and_weeks_start_the_stage_after_they_end() {
+        let args = parse(r#"stages(warn = "2024-Q1", error = "2024-W14")"#).unwrap();
+        let stage = |year, month, day| {
+            args.active_stage(chrono::NaiveDate::from_ymd_opt(year, month, day).unwrap())
+                .map(|stage| stage.stage)
+        };
+        assert_eq!(stage(2024, 3, 31), None);
+        assert_eq!(stage(2024, 4, 1), Some(Stage::Warn));
+        // Week 14 of 2024 ends on Sunday, April 7.
+        assert_eq!(stage(2024, 4, 7), Some(Stage::Warn));
+        assert_eq!(stage(2024, 4, 8), Some(Stage::Error));
+    }
+
+    #[test]
+    fn messages_name_the_column_without_a_subspan() {
+        let message =
+            |input: &str| located_message(input, &BestBeforeDate::parse(input).unwrap_err());
+        assert_eq!(
+            message("2023-02-29"),
+            "29 is not a day of 2023-02; did you mean 2023-02-28? \
+             (at column 9 of \"2023-02-29\": '29')"
+        );
+        assert_eq!(
+            message("Q5.2024"),
+            "5 is not a quarter; did you mean Q4.2024? (at column 2 of \"Q5.2024\": '5')"
+        );
+        // Errors about the whole date need no location.
+        assert_eq!(
+            message("2024/03"),
+            BestBeforeDate::parse("2024/03").unwrap_err().message
+        );
+    }
+}
@@ -0,0 +1,284 @@
+use crate::date::BestBeforeDate;
+use crate::environment;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Utc};
+use std::fmt;
+
+/// The time zone used to turn an instant into the current day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeZone {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl TimeZone {
+    /// Parses "UTC", "local" or a fixed offset such as "+02:00", "-0530" or "+2".
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let trimmed = value.trim();
+        if trimmed.eq_ignore_ascii_case("utc") || trimmed == "Z" {
+            return Ok(TimeZone::Utc);
+        }
+        if trimmed.eq_ignore_ascii_case("local") {
+            return Ok(TimeZone::Local);
+        }
+
+        let invalid = || {
+            format!(
+                "'{}' is not a time zone. Expected 'UTC', 'local' or an offset such as '+02:00'",
+                value
+            )
+        };
+        let (sign, rest) = match trimmed.as_bytes().first() {
+            Some(b'+') => (1, &trimmed[1..]),
+            Some(b'-') => (-1, &trimmed[1..]),
+            _ => return Err(invalid()),
+        };
+        let (hours, minutes) = match rest.split_once(':') {
+            Some((hours, minutes)) => (hours, minutes),
+            None if rest.len() == 4 => rest.split_at(2),
+            None => (rest, "0"),
+        };
+        let hours: i32 = hours.parse().map_err(|_| invalid())?;
+        let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+        if hours > 14 || minutes > 59 {
+            return Err(invalid());
+        }
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+            .map(TimeZone::Fixed)
+            .ok_or_else(invalid)
+    }
+
+    fn today(self, instant: DateTime<Utc>) -> NaiveDate {
+        match self {
+            TimeZone::Utc => instant.date_naive(),
+            TimeZone::Local => instant.with_timezone(&Local).date_naive(),
+            TimeZone::Fixed(offset) => instant.with_timezone(&offset).date_naive(),
+        }
+    }
+}
+
+impl fmt::Display for TimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeZone::Utc => write!(f, "UTC"),
+            TimeZone::Local => write!(f, "local time"),
+            TimeZone::Fixed(offset) => write!(f, "UTC{}", offset),
+        }
+    }
+}
+
+/// Where the current day was taken from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClockSource {
+    /// A fixed date was given, by `BESTBEFORE_DATE` or the named command line option.
+    Override(&'static str, String),
+    /// `SOURCE_DATE_EPOCH` was set, as done by reproducible-build pipelines.
+    SourceDateEpoch(i64, TimeZone),
+    /// The system clock.
+    System(TimeZone),
+}
+
+/// The day annotations are evaluated against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clock {
+    pub today: NaiveDate,
+    pub source: ClockSource,
+}
+
+impl Clock {
+    /// Resolves the current day from the environment.
+    ///
+    /// In order of precedence:
+    /// 1. `BESTBEFORE_DATE`, in any of the supported date formats
+    /// 2. `SOURCE_DATE_EPOCH`, seconds since the Unix epoch
+    /// 3. the system clock
+    ///
+    /// `BESTBEFORE_TZ` selects the time zone used for the latter two, defaulting to UTC so the
+    /// result does not depend on the build machine's settings.
+    pub fn from_env() -> Result<Self, String> {
+        Clock::from_vars(|name| environment::var(name).ok(), Utc::now())
+    }
+
+    /// Resolves the current day as [`Clock::from_env`] does, with the variables looked up by
+    /// `var` and the system clock standing at `now`.
+    fn from_vars(var: impl Fn(&str) -> Option<String>, now: DateTime<Utc>) -> Result<Self, String> {
+        if let Some(value) = var("BESTBEFORE_DATE") {
+            return Clock::fixed("BESTBEFORE_DATE", &value).map_err(|message| {
+                format!("Invalid BESTBEFORE_DATE environment variable: {}", message)
+            });
+        }
+
+        let time_zone = match var("BESTBEFORE_TZ") {
+            Some(value) => TimeZone::parse(&value)
+                .map_err(|err| format!("Invalid BESTBEFORE_TZ environment variable: {}", err))?,
+            None => TimeZone::Utc,
+        };
+
+        if let Some(value) = var("SOURCE_DATE_EPOCH") {
+            let seconds: i64 = value.trim().parse().map_err(|_| {
+                format!(
+                    "Invalid SOURCE_DATE_EPOCH environment variable: '{}'. Expected seconds since the Unix epoch",
+                    value
+                )
+            })?;
+            let instant = DateTime::from_timestamp(seconds, 0).ok_or_else(|| {
+                format!(
+                    "Invalid SOURCE_DATE_EPOCH environment variable: {} is out of range",
+                    seconds
+                )
+            })?;
+            return Ok(Clock {
+                today: time_zone.today(instant),
+                source: ClockSource::SourceDateEpoch(seconds, time_zone),
+            });
+        }
+
+        Ok(Clock {
+            today: time_zone.today(now),
+            source: ClockSource::System(time_zone),
+        })
+    }
+
+    /// A clock stopped at the first day of the period `value`, given by `origin`.
+    pub fn fixed(origin: &'static str, value: &str) -> Result<Self, String> {
+        let date = BestBeforeDate::parse(value).map_err(|err| err.message)?;
+        Ok(Clock {
+            today: date.start(),
+            source: ClockSource::Override(origin, value.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let today = self.today.format("%Y-%m-%d");
+        match &self.source {
+            ClockSource::Override(origin, value) => {
+                write!(f, "{} from {}=\"{}\"", today, origin, value)
+            }
+            ClockSource::SourceDateEpoch(seconds, time_zone) => {
+                write!(
+                    f,
+                    "{} from SOURCE_DATE_EPOCH={} ({})",
+                    today, seconds, time_zone
+                )
+            }
+            ClockSource::System(time_zone) => {
+                write!(f, "{} from the system clock ({})", today, time_zone)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    /// 2024-03-31T22:30:00Z, which is already April in most of Europe.
+    const EPOCH: i64 = 1_711_924_200;
+
+    /// The clock resolved from `vars` while the system clock stands at [`EPOCH`].
+    fn resolve(vars: &[(&str, &str)]) -> Result<Clock, String> {
+        let var = |name: &str| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        };
+        Clock::from_vars(var, DateTime::from_timestamp(EPOCH, 0).unwrap())
+    }
+
+    #[test]
+    fn time_zones() {
+        assert_eq!(TimeZone::parse("UTC"), Ok(TimeZone::Utc));
+        assert_eq!(TimeZone::parse(" utc "), Ok(TimeZone::Utc));
+        assert_eq!(TimeZone::parse("Z"), Ok(TimeZone::Utc));
+        assert_eq!(TimeZone::parse("Local"), Ok(TimeZone::Local));
+        let offset = |seconds| Ok(TimeZone::Fixed(FixedOffset::east_opt(seconds).unwrap()));
+        assert_eq!(TimeZone::parse("+02:00"), offset(7200));
+        assert_eq!(TimeZone::parse("-0530"), offset(-19800));
+        assert_eq!(TimeZone::parse("+2"), offset(7200));
+        assert_eq!(TimeZone::parse("+14:00"), offset(50400));
+        for invalid in ["Europe/Berlin", "02:00", "+15", "+02:60", "+", ""] {
+            assert!(TimeZone::parse(invalid).is_err(), "{}", invalid);
+        }
+        assert_eq!(TimeZone::parse("+0200").unwrap().to_string(), "UTC+02:00");
+    }
+
+    #[test]
+    fn system_clock_in_utc_by_default() {
+        let clock = resolve(&[]).unwrap();
+        assert_eq!(clock.today, day("2024-03-31"));
+        assert_eq!(clock.source, ClockSource::System(TimeZone::Utc));
+        assert_eq!(clock.to_string(), "2024-03-31 from the system clock (UTC)");
+    }
+
+    #[test]
+    fn time_zone_applies_to_the_system_clock() {
+        let clock = resolve(&[("BESTBEFORE_TZ", "+02:00")]).unwrap();
+        assert_eq!(clock.today, day("2024-04-01"));
+    }
+
+    #[test]
+    fn source_date_epoch_overrides_the_system_clock() {
+        let clock = resolve(&[("SOURCE_DATE_EPOCH", "0")]).unwrap();
+        assert_eq!(clock.today, day("1970-01-01"));
+        let clock = resolve(&[
+            ("SOURCE_DATE_EPOCH", &EPOCH.to_string()),
+            ("BESTBEFORE_TZ", "+01:00"),
+        ])
+        .unwrap();
+        assert_eq!(clock.today, day("2024-03-31"));
+        assert_eq!(
+            clock.to_string(),
+            "2024-03-31 from SOURCE_DATE_EPOCH=1711924200 (UTC+01:00)"
+        );
+    }
+
+    #[test]
+    fn bestbefore_date_overrides_everything() {
+        let clock = resolve(&[
+            ("BESTBEFORE_DATE", "2025-Q3"),
+            ("SOURCE_DATE_EPOCH", "0"),
+            ("BESTBEFORE_TZ", "+02:00"),
+        ])
+        .unwrap();
+        assert_eq!(clock.today, day("2025-07-01"));
+        assert_eq!(
+            clock.to_string(),
+            "2025-07-01 from BESTBEFORE_DATE=\"2025-Q3\""
+        );
+        // The time zone is not even read, so an invalid one does no harm.
+        let clock = resolve(&[("BESTBEFORE_DATE", "2025-07-15"), ("BESTBEFORE_TZ", "Mars")]);
+        assert_eq!(clock.unwrap().today, day("2025-07-15"));
+    }
+
+    #[test]
+    fn invalid_overrides() {
+        assert_eq!(
+            resolve(&[("BESTBEFORE_DATE", "13.2025")]),
+            Err(
+                "Invalid BESTBEFORE_DATE environment variable: 13 is not a month; \
+                 did you mean 12.2025?"
+                    .to_string()
+            )
+        );
+        let err = resolve(&[("SOURCE_DATE_EPOCH", "yesterday")]).unwrap_err();
+        assert!(
+            err.starts_with("Invalid SOURCE_DATE_EPOCH environment variable: 'yesterday'"),
+            "{}",
+            err
+        );
+        let err = resolve(&[("SOURCE_DATE_EPOCH", &i64::MAX.to_string())]).unwrap_err();
+        assert!(err.ends_with("is out of range"), "{}", err);
+        let err = resolve(&[("BESTBEFORE_TZ", "CET"), ("SOURCE_DATE_EPOCH", "0")]).unwrap_err();
+        assert!(
+            err.starts_with("Invalid BESTBEFORE_TZ environment variable: 'CET'"),
+            "{}",
+            err
+        );
+    }
+}
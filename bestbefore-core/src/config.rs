@@ -0,0 +1,734 @@
+//! Project-wide configuration.
+//!
+//! The configuration is taken from the first of these found from `CARGO_MANIFEST_DIR` upward,
+//! stopping at the workspace root, or at the package itself outside of a workspace:
+//! * a `bestbefore.toml` file
+//! * the `[package.metadata.bestbefore]` table of a `Cargo.toml`
+//! * the `[workspace.metadata.bestbefore]` table of a `Cargo.toml`
+//!
+//! All of them accept the same keys:
+//!
+//! ```toml
+//! mode = "normal"
+//! grace_period = "3 months"
+//! required = ["owner", "ticket"]
+//! ticket_url = "https://tracker.local/browse/{ticket}"
+//! ticket_pattern = "[A-Z]+-[0-9]+"
+//! report = "target/bestbefore.jsonl"
+//!
+//! [messages]
+//! warn = "{item} is past its best-before date ({date}), ask {owner}"
+//! error = "{item} has expired ({date}), see {ticket}"
+//!
+//! [policy]
+//! max_snooze = "6 months"
+//! max_extensions = 2
+//!
+//! [[snooze]]
+//! id = "legacy-fees"
+//! until = "2026-03"
+//! reason = "Blocked on the billing migration"
+//! approver = "alice"
+//! extensions = 1
+//! ```
+
+use crate::args::{BestBeforeArgs, SpannedDate, Stage, StageDate};
+use crate::date::{BestBeforeDate, Period};
+use crate::mode::Mode;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex, PoisonError};
+use std::time::SystemTime;
+use toml::{Table, Value};
+
+/// Parameters that can be made mandatory with `required`.
+const REQUIRABLE: &[&str] = &["owner", "ticket", "replacement", "message", "expires"];
+
+/// Keys of a `[[snooze]]` entry.
+const SNOOZE_KEYS: &[&str] = &["id", "item", "until", "reason", "approver", "extensions"];
+
+/// Keys of the `[policy]` table.
+const POLICY_KEYS: &[&str] = &["max_snooze", "max_extensions"];
+
+/// Placeholders available in message templates.
+const PLACEHOLDERS: &[&str] = &["item", "date", "owner", "ticket"];
+
+#[derive(Debug, Default, Clone)]
+pub struct Config {
+    /// Where the configuration was read from, if anywhere.
+    pub path: Option<PathBuf>,
+    /// Time between the last configured stage and expiry, for annotations without `expires`.
+    pub grace_period: Option<Period>,
+    /// Parameters every annotation has to give.
+    pub required: Vec<String>,
+    /// Message templates by stage, replacing the built-in default messages.
+    pub messages: Vec<(Stage, String)>,
+    pub ticket_url: Option<String>,
+    pub ticket_pattern: Option<String>,
+    /// Enforcement mode, unless overridden by `BESTBEFORE_MODE`.
+    pub mode: Option<Mode>,
+    /// Where to append a record of every evaluated annotation, see [`crate::report`].
+    pub report: Option<PathBuf>,
+    /// The limits of snoozes, which every configuration with snoozes has to set.
+    pub policy: Option<Policy>,
+    /// Approved postponements of expiry.
+    pub snoozes: Vec<Snooze>,
+}
+
+/// How far and how often the project lets deadlines slip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    /// How long after an annotation's deadline a snooze may last at most.
+    pub max_snooze: Period,
+    /// How many times an annotation may be snoozed.
+    pub max_extensions: u32,
+}
+
+/// What a snooze applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnoozeKey {
+    /// The annotation with this `id`.
+    Id(String),
+    /// The annotated item with this path, such as `billing::legacy::fee`.
+    Item(String),
+}
+
+/// An approved postponement of an annotation's expiry.
+///
+/// Until `until` has passed, the annotation warns instead of failing the build. Entries have to
+/// stay within the [`Policy`] of the project, so a deadline cannot slip indefinitely.
+#[derive(Debug, Clone)]
+pub struct Snooze {
+    pub key: SnoozeKey,
+    pub until: BestBeforeDate,
+    pub reason: String,
+    pub approver: String,
+    /// How many times the deadline has been postponed, including this time.
+    pub extensions: u32,
+}
+
+impl Snooze {
+    fn from_table(table: &Table) -> Result<Self, String> {
+        let text = |key: &str| -> Result<Option<String>, String> {
+            table
+                .get(key)
+                .map(|value| string(key, value).map(str::to_string))
+                .transpose()
+        };
+        let required = |key: &str| -> Result<String, String> {
+            text(key)?.ok_or_else(|| format!("every [[snooze]] entry needs '{}'", key))
+        };
+        let date = |key: &str| -> Result<BestBeforeDate, String> {
+            BestBeforeDate::parse(&required(key)?)
+                .map_err(|err| format!("snooze {}: {}", key, err.message))
+        };
+        let count = |key: &str| -> Result<Option<u32>, String> {
+            table
+                .get(key)
+                .map(|value| {
+                    value
+                        .as_integer()
+                        .and_then(|count| u32::try_from(count).ok())
+                        .ok_or_else(|| format!("snooze {} must be a non-negative integer", key))
+                })
+                .transpose()
+        };
+
+        for key in table.keys() {
+            if !SNOOZE_KEYS.contains(&key.as_str()) {
+                return Err(format!(
+                    "Unknown key '{}' in [[snooze]], expected one of {}",
+                    key,
+                    SNOOZE_KEYS.join(", ")
+                ));
+            }
+        }
+
+        let key = match (text("id")?, text("item")?) {
+            (Some(id), None) => SnoozeKey::Id(id),
+            (None, Some(item)) => SnoozeKey::Item(item),
+            _ => {
+                return Err(
+                    "every [[snooze]] entry needs either 'id' or 'item', but not both".to_string(),
+                )
+            }
+        };
+        Ok(Snooze {
+            until: date("until")?,
+            reason: required("reason")?,
+            approver: required("approver")?,
+            extensions: count("extensions")?.unwrap_or(1),
+            key,
+        })
+    }
+
+    /// Whether the snooze still holds on `today`.
+    pub fn is_active(&self, today: chrono::NaiveDate) -> bool {
+        !self.until.is_past(today)
+    }
+}
+
+impl fmt::Display for SnoozeKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnoozeKey::Id(id) => write!(f, "id '{}'", id),
+            SnoozeKey::Item(item) => write!(f, "item '{}'", item),
+        }
+    }
+}
+
+impl fmt::Display for Snooze {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "until {} by {} ({})",
+            self.until, self.approver, self.reason
+        )
+    }
+}
+
+impl Policy {
+    fn from_table(table: &Table) -> Result<Self, String> {
+        for key in table.keys() {
+            if !POLICY_KEYS.contains(&key.as_str()) {
+                return Err(format!(
+                    "Unknown key '{}' in [policy], expected one of {}",
+                    key,
+                    POLICY_KEYS.join(", ")
+                ));
+            }
+        }
+        let required = |key: &str| {
+            table
+                .get(key)
+                .ok_or_else(|| format!("[policy] needs '{}'", key))
+        };
+        let max_snooze = string("max_snooze", required("max_snooze")?)?;
+        let max_extensions = required("max_extensions")?
+            .as_integer()
+            .and_then(|count| u32::try_from(count).ok())
+            .ok_or_else(|| "max_extensions must be a non-negative integer".to_string())?;
+        Ok(Policy {
+            max_snooze: Period::parse(max_snooze)
+                .map_err(|message| format!("max_snooze: {}", message))?,
+            max_extensions,
+        })
+    }
+}
+
+impl Config {
+    /// Finds and reads the configuration of the crate being compiled.
+    ///
+    /// Returns the default configuration if there is none.
+    pub fn discover() -> Result<Self, String> {
+        match env::var("CARGO_MANIFEST_DIR") {
+            Ok(manifest_dir) => Config::discover_from(Path::new(&manifest_dir)),
+            Err(_) => Ok(Config::default()),
+        }
+    }
+
+    /// Finds and reads the configuration of the package in `package_dir`.
+    ///
+    /// The result is kept for as long as none of the files looked at changes, as every annotation
+    /// of a crate needs it.
+    pub fn discover_from(package_dir: &Path) -> Result<Self, String> {
+        static DISCOVERIES: LazyLock<Mutex<HashMap<PathBuf, Discovery>>> =
+            LazyLock::new(Default::default);
+
+        let mut discoveries = DISCOVERIES.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(discovery) = discoveries.get(package_dir) {
+            if discovery.is_current() {
+                return discovery.result.clone();
+            }
+        }
+        let discovery = Discovery::search(package_dir);
+        let result = discovery.result.clone();
+        discoveries.insert(package_dir.to_path_buf(), discovery);
+        result
+    }
+
+    /// Reads the configuration from `text`, the content of the `bestbefore.toml` or `Cargo.toml`
+    /// at `path`.
+    ///
+    /// Returns `None` for a `Cargo.toml` without configuration.
+    pub fn parse(path: &Path, text: &str) -> Result<Option<Self>, String> {
+        let table = parse_table(path, text)?;
+        if path.file_name().is_some_and(|name| name == "Cargo.toml") {
+            Config::from_manifest(path, &table)
+        } else {
+            Config::from_table(path.to_path_buf(), &table).map(Some)
+        }
+    }
+
+    /// The configuration in the metadata of the manifest at `path`, if any.
+    fn from_manifest(path: &Path, table: &Table) -> Result<Option<Self>, String> {
+        for section in ["package", "workspace"] {
+            let metadata = table
+                .get(section)
+                .and_then(|section| section.get("metadata"))
+                .and_then(|metadata| metadata.get("bestbefore"));
+            if let Some(metadata) = metadata {
+                let Value::Table(metadata) = metadata else {
+                    return Err(format!(
+                        "Invalid configuration in {}: [{}.metadata.bestbefore] must be a table",
+                        path.display(),
+                        section
+                    ));
+                };
+                return Config::from_table(path.to_path_buf(), metadata).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    fn from_table(path: PathBuf, table: &Table) -> Result<Self, String> {
+        let error =
+            |message: String| format!("Invalid configuration in {}: {}", path.display(), message);
+        let mut config = Config::default();
+
+        for (key, value) in table {
+            match key.as_str() {
+                "grace_period" => {
+                    let period = string(key, value).map_err(error)?;
+                    config.grace_period = Some(
+                        Period::parse(period)
+                            .map_err(|message| error(format!("grace_period: {}", message)))?,
+                    );
+                }
+                "required" => {
+                    let Value::Array(values) = value else {
+                        return Err(error(format!(
+                            "required must be a list of parameter names such as {:?}",
+                            REQUIRABLE
+                        )));
+                    };
+                    for value in values {
+                        let name = string(key, value).map_err(error)?;
+                        if !REQUIRABLE.contains(&name) {
+                            return Err(error(format!(
+                                "'{}' cannot be required, expected one of {:?}",
+                                name, REQUIRABLE
+                            )));
+                        }
+                        config.required.push(name.to_string());
+                    }
+                }
+                "messages" => {
+                    let Value::Table(messages) = value else {
+                        return Err(error("messages must be a table".to_string()));
+                    };
+                    for (stage, template) in messages {
+                        let stage = Stage::from_name(stage).ok_or_else(|| {
+                            error(format!(
+                                "Unknown stage 'messages.{}', expected one of 'note', 'warn', 'deny_in_ci' or 'error'",
+                                stage
+                            ))
+                        })?;
+                        let template = string(key, template).map_err(error)?;
+                        check_placeholders(template).map_err(error)?;
+                        config.messages.push((stage, template.to_string()));
+                    }
+                }
+                "mode" => {
+                    let mode = string(key, value).map_err(error)?;
+                    config.mode =
+                        Some(Mode::parse(mode).map_err(|message| error(format!("mode: {}", message)))?);
+                }
+                "snooze" => {
+                    let Value::Array(entries) = value else {
+                        return Err(error(
+                            "snooze must be a list of [[snooze]] entries".to_string(),
+                        ));
+                    };
+                    for entry in entries {
+                        let Value::Table(entry) = entry else {
+                            return Err(error(
+                                "snooze must be a list of [[snooze]] entries".to_string(),
+                            ));
+                        };
+                        let snooze = Snooze::from_table(entry).map_err(error)?;
+                        if config.snoozes.iter().any(|other| other.key == snooze.key) {
+                            return Err(error(format!(
+                                "{} is snoozed more than once",
+                                snooze.key
+                            )));
+                        }
+                        config.snoozes.push(snooze);
+                    }
+                }
+                "policy" => {
+                    let Value::Table(policy) = value else {
+                        return Err(error("policy must be a table".to_string()));
+                    };
+                    config.policy = Some(Policy::from_table(policy).map_err(error)?);
+                }
+                "report" => config.report = Some(string(key, value).map_err(error)?.into()),
+                "ticket_url" => config.ticket_url = Some(string(key, value).map_err(error)?.into()),
+                "ticket_pattern" => {
+                    config.ticket_pattern = Some(string(key, value).map_err(error)?.into())
+                }
+                other => {
+                    return Err(error(format!(
+                        "Unknown key '{}', expected one of 'mode', 'grace_period', 'required', 'messages', 'policy', 'snooze', 'report', 'ticket_url' or 'ticket_pattern'",
+                        other
+                    )))
+                }
+            }
+        }
+
+        if !config.snoozes.is_empty() {
+            let Some(policy) = config.policy else {
+                return Err(error(
+                    "[[snooze]] entries need limits, set max_snooze and max_extensions in [policy]"
+                        .to_string(),
+                ));
+            };
+            for snooze in &config.snoozes {
+                if snooze.extensions > policy.max_extensions {
+                    return Err(error(format!(
+                        "snooze of {} has been extended {} times, but the policy allows {}",
+                        snooze.key, snooze.extensions, policy.max_extensions
+                    )));
+                }
+            }
+        }
+
+        config.path = Some(path);
+        Ok(config)
+    }
+
+    /// How the configuration is referred to in diagnostics.
+    pub fn origin(&self) -> String {
+        match &self.path {
+            Some(path) => path.display().to_string(),
+            None => "the default configuration".to_string(),
+        }
+    }
+
+    /// The message template for `stage`, if one is configured.
+    pub fn message(&self, stage: Stage) -> Option<&str> {
+        self.messages
+            .iter()
+            .find(|(configured, _)| *configured == stage)
+            .map(|(_, template)| template.as_str())
+    }
+
+    /// The snooze for the annotation with `id` on the item at `path`, if any.
+    ///
+    /// A snooze by id takes precedence over one by item path.
+    pub fn snooze(&self, id: Option<&str>, path: Option<&str>) -> Option<&Snooze> {
+        let by_id = id.and_then(|id| {
+            self.snoozes
+                .iter()
+                .find(|snooze| snooze.key == SnoozeKey::Id(id.to_string()))
+        });
+        by_id.or_else(|| {
+            path.and_then(|path| {
+                self.snoozes
+                    .iter()
+                    .find(|snooze| snooze.key == SnoozeKey::Item(path.to_string()))
+            })
+        })
+    }
+
+    /// Checks that `snooze` of the annotation with `args` ends within the policy, counting from
+    /// the annotation's own deadline.
+    pub fn check_snooze(&self, snooze: &Snooze, args: &BestBeforeArgs) -> syn::Result<()> {
+        let Some(policy) = self.policy else {
+            return Ok(());
+        };
+        let deadline = args.stages[args.stages.len() - 1].date;
+        let Some(limit) = policy.max_snooze.after(deadline.value.end()) else {
+            return Ok(());
+        };
+        if snooze.until.end() > limit {
+            return Err(syn::Error::new(
+                deadline.span,
+                format!(
+                    "The snooze of {} until {} goes past the limit of {}: at most {} after the deadline {}, which is {}",
+                    snooze.key,
+                    snooze.until,
+                    self.origin(),
+                    policy.max_snooze,
+                    deadline.value,
+                    limit
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the required parameters of `args` and adds an expiry after the grace period.
+    pub fn apply_to(&self, args: &mut BestBeforeArgs) -> syn::Result<()> {
+        for name in &self.required {
+            let given = match name.as_str() {
+                "owner" => args.owner.is_some(),
+                "ticket" => args.ticket.is_some(),
+                "replacement" => args.replacement.is_some(),
+                "message" => args.warn_message.is_some() || args.expire_message.is_some(),
+                _ => args.stages.iter().any(|stage| stage.stage == Stage::Error),
+            };
+            if !given {
+                return Err(syn::Error::new(
+                    args.span,
+                    format!(
+                        "Missing parameter '{}', which {} requires on every annotation",
+                        name,
+                        self.origin()
+                    ),
+                ));
+            }
+        }
+
+        if let Some(period) = self.grace_period {
+            let last = args.stages[args.stages.len() - 1];
+            if last.stage != Stage::Error {
+                if let Some(day) = period.after(last.date.value.end()) {
+                    args.stages.push(StageDate {
+                        stage: Stage::Error,
+                        date: SpannedDate {
+                            value: BestBeforeDate::on(day),
+                            span: last.date.span,
+                        },
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Substitutes `{name}` placeholders in `template`.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+fn check_placeholders(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 1..start + end];
+        if !PLACEHOLDERS.contains(&name) {
+            return Err(format!(
+                "Unknown placeholder '{{{}}}' in message template, expected one of {}",
+                name,
+                PLACEHOLDERS
+                    .iter()
+                    .map(|placeholder| format!("{{{}}}", placeholder))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        rest = &rest[start + end..];
+    }
+    Ok(())
+}
+
+fn string<'a>(key: &str, value: &'a Value) -> Result<&'a str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("{} must be a string", key))
+}
+
+/// The configuration found for a package, along with the files the search looked at.
+struct Discovery {
+    /// The files looked for, with their modification time if they exist.
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    result: Result<Config, String>,
+}
+
+impl Discovery {
+    fn search(package_dir: &Path) -> Self {
+        let mut files = Vec::new();
+        let result = Discovery::find(package_dir, &mut files);
+        Discovery { files, result }
+    }
+
+    /// Searches from `package_dir` up to the workspace root, which is the first directory with a
+    /// lock file or with a manifest that declares a workspace.
+    fn find(
+        package_dir: &Path,
+        files: &mut Vec<(PathBuf, Option<SystemTime>)>,
+    ) -> Result<Config, String> {
+        let mut exists = |path: &Path| {
+            let modified = modified(path);
+            files.push((path.to_path_buf(), modified));
+            modified.is_some()
+        };
+        for dir in package_dir.ancestors() {
+            let file = dir.join("bestbefore.toml");
+            if exists(&file) {
+                return Ok(Config::parse(&file, &read(&file)?)?.unwrap_or_default());
+            }
+
+            let manifest = dir.join("Cargo.toml");
+            if exists(&manifest) {
+                let text = read(&manifest)?;
+                if let Some(config) = Config::parse(&manifest, &text)? {
+                    return Ok(config);
+                }
+                if parse_table(&manifest, &text)?.contains_key("workspace") {
+                    break;
+                }
+            }
+            // Cargo writes the lock file next to the root manifest before compiling anything, so
+            // a package outside of a workspace stops at its own directory.
+            if exists(&dir.join("Cargo.lock")) {
+                break;
+            }
+        }
+        Ok(Config::default())
+    }
+
+    /// Returns `true` if none of the files looked at has been created, changed or removed since.
+    fn is_current(&self) -> bool {
+        self.files
+            .iter()
+            .all(|(path, modified)| self::modified(path) == *modified)
+    }
+}
+
+/// The modification time of the file at `path`, if there is one.
+fn modified(path: &Path) -> Option<SystemTime> {
+    let metadata = fs::metadata(path)
+        .ok()
+        .filter(|metadata| metadata.is_file())?;
+    Some(metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH))
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|err| format!("Cannot read {}: {}", path.display(), err))
+}
+
+fn parse_table(path: &Path, text: &str) -> Result<Table, String> {
+    text.parse::<Table>()
+        .map_err(|err| format!("Invalid configuration in {}: {}", path.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory for `name`, with the given files.
+    fn tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = env::temp_dir().join(format!("bestbefore-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for (path, text) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        root
+    }
+
+    const PACKAGE: &str = "[package]\nname = \"app\"\n";
+
+    #[test]
+    fn standalone_packages_stop_at_their_own_directory() {
+        let root = tree(
+            "standalone",
+            &[
+                ("bestbefore.toml", "mode = \"strict\""),
+                ("app/Cargo.toml", PACKAGE),
+                ("app/Cargo.lock", ""),
+            ],
+        );
+        let config = Config::discover_from(&root.join("app")).unwrap();
+        assert_eq!(config.path, None);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn members_use_the_workspace_configuration() {
+        let root = tree(
+            "workspace",
+            &[
+                (
+                    "Cargo.toml",
+                    "[workspace]\n[workspace.metadata.bestbefore]\nmode = \"strict\"\n",
+                ),
+                ("Cargo.lock", ""),
+                ("crates/app/Cargo.toml", PACKAGE),
+            ],
+        );
+        let config = Config::discover_from(&root.join("crates/app")).unwrap();
+        assert_eq!(config.path, Some(root.join("Cargo.toml")));
+        assert_eq!(config.mode, Some(Mode::Strict));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn changed_configuration_is_read_again() {
+        let root = tree("changed", &[("Cargo.toml", PACKAGE), ("Cargo.lock", "")]);
+        assert_eq!(Config::discover_from(&root).unwrap().path, None);
+        fs::write(root.join("bestbefore.toml"), "mode = \"strict\"").unwrap();
+        let config = Config::discover_from(&root).unwrap();
+        assert_eq!(config.path, Some(root.join("bestbefore.toml")));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    const SNOOZE: &str = r#"
+        [[snooze]]
+        id = "fees"
+        until = "2030-06"
+        reason = "Blocked on the billing migration"
+        approver = "alice"
+        extensions = 2
+    "#;
+
+    fn parse(text: &str) -> Result<Config, String> {
+        Config::parse(Path::new("bestbefore.toml"), text).map(Option::unwrap)
+    }
+
+    #[test]
+    fn snoozes_need_a_policy() {
+        let err = parse(SNOOZE).unwrap_err();
+        assert!(
+            err.ends_with("set max_snooze and max_extensions in [policy]"),
+            "{}",
+            err
+        );
+
+        let policy = "[policy]\nmax_snooze = \"1 quarter\"\nmax_extensions = 2\n";
+        let config = parse(&format!("{}{}", policy, SNOOZE)).unwrap();
+        assert_eq!(
+            config.policy,
+            Some(Policy {
+                max_snooze: Period::Months(3),
+                max_extensions: 2
+            })
+        );
+    }
+
+    #[test]
+    fn snoozes_stay_within_the_extensions_of_the_policy() {
+        let policy = "[policy]\nmax_snooze = \"1 year\"\nmax_extensions = 1\n";
+        let err = parse(&format!("{}{}", policy, SNOOZE)).unwrap_err();
+        assert!(
+            err.ends_with("snooze of id 'fees' has been extended 2 times, but the policy allows 1"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn limits_are_only_set_by_the_policy() {
+        let err = parse(&format!("{}max_until = \"2031-01\"\n", SNOOZE)).unwrap_err();
+        assert!(
+            err.contains("Unknown key 'max_until' in [[snooze]]"),
+            "{}",
+            err
+        );
+        let err = parse("[policy]\nmax_snooze = \"1 year\"\n").unwrap_err();
+        assert!(err.ends_with("[policy] needs 'max_extensions'"), "{}", err);
+    }
+}
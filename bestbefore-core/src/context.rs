@@ -0,0 +1,282 @@
+//! Evaluating annotations with everything they depend on besides their own arguments.
+//!
+//! The macro and the `cargo-bestbefore` command share this module, so both come to the same
+//! verdict for the same annotation.
+
+use crate::args::BestBeforeArgs;
+use crate::clock::Clock;
+use crate::config::{Config, Snooze};
+use crate::eval::{evaluate, Verdict};
+use crate::location::Location;
+use crate::mode::{running_in_ci, Mode};
+use crate::report;
+use crate::target;
+use crate::ticket::TicketConfig;
+
+/// The annotated code, as referred to in diagnostics and in the configuration.
+pub struct Subject {
+    /// How the code is referred to in diagnostics, such as "struct Config".
+    pub name: String,
+    /// The name of the crate the code is in.
+    pub krate: Option<String>,
+    /// The path of the annotated item, such as `billing::legacy::Config`, if it has one.
+    pub path: Option<String>,
+    /// Where the annotation is, if known.
+    pub location: Option<Location>,
+}
+
+/// The outcome of an annotation.
+pub struct Outcome<'a> {
+    pub verdict: Verdict,
+    /// The details reported along with the diagnostic, as collected by [`Context::assess`].
+    pub details: Vec<(&'static str, String)>,
+    /// The snooze entry for the annotation, whether active or not.
+    pub snooze: Option<&'a Snooze>,
+}
+
+/// Everything an evaluation depends on besides the annotation's own arguments.
+pub struct Context {
+    pub clock: Clock,
+    pub config: Config,
+    pub tickets: TicketConfig,
+    pub mode: Mode,
+}
+
+impl Context {
+    /// The context of the crate being compiled.
+    pub fn from_env() -> Result<Self, String> {
+        Context::new(Config::discover()?, Clock::from_env()?)
+    }
+
+    /// The context for `config` on the day of `clock`, with settings from the environment.
+    pub fn new(config: Config, clock: Clock) -> Result<Self, String> {
+        Ok(Context {
+            clock,
+            tickets: TicketConfig::resolve(&config)?,
+            mode: Mode::resolve(&config)?,
+            config,
+        })
+    }
+
+    /// Completes `args` from the configuration and evaluates them for `subject`.
+    ///
+    /// Fails if the annotation does not satisfy the configuration, or its snooze goes past the
+    /// policy, whether or not a diagnostic is due.
+    pub fn assess(&self, args: &mut BestBeforeArgs, subject: &Subject) -> syn::Result<Outcome<'_>> {
+        self.config.apply_to(args)?;
+        let snooze = self
+            .config
+            .snooze(args.id.as_deref(), subject.path.as_deref());
+        if let Some(snooze) = snooze {
+            self.config.check_snooze(snooze, args)?;
+        }
+        let details = self.details(args, snooze)?;
+        Ok(Outcome {
+            verdict: self.evaluate(args, &subject.name, snooze),
+            details,
+            snooze,
+        })
+    }
+
+    /// Evaluates `args` for the code named `name`, honoring the mode and `snooze`.
+    ///
+    /// An active snooze is an approved exception, so it turns errors into warnings after the mode
+    /// is applied: `strict` does not override it.
+    fn evaluate(&self, args: &BestBeforeArgs, name: &str, snooze: Option<&Snooze>) -> Verdict {
+        let verdict = evaluate(args, name, self.clock.today, running_in_ci(), &self.config);
+        match self.mode.enforce(verdict) {
+            Verdict::Fail { date, message }
+                if snooze.is_some_and(|snooze| snooze.is_active(self.clock.today)) =>
+            {
+                Verdict::Warn {
+                    date,
+                    message,
+                    loud: true,
+                }
+            }
+            verdict => verdict,
+        }
+    }
+
+    /// The details reported along with every diagnostic of an annotation.
+    ///
+    /// Fails if the annotation's ticket does not match the configured pattern.
+    fn details(
+        &self,
+        args: &BestBeforeArgs,
+        snooze: Option<&Snooze>,
+    ) -> syn::Result<Vec<(&'static str, String)>> {
+        let mut details = Vec::new();
+        if let Some(owner) = &args.owner {
+            details.push(("owner", owner.clone()));
+        }
+        if let Some(ticket) = &args.ticket {
+            self.tickets
+                .check(&ticket.value())
+                .map_err(|message| syn::Error::new(ticket.span(), message))?;
+            details.push(("ticket", self.tickets.link(&ticket.value())));
+        }
+        if let Some(replacement) = &args.replacement {
+            details.push((
+                "help",
+                format!("use `{}` instead", target::render_tokens(replacement)),
+            ));
+        }
+        if let Some(snooze) = snooze {
+            let mut text = snooze.to_string();
+            if let Some(policy) = self.config.policy {
+                text.push_str(&format!(
+                    ", extension {} of {}",
+                    snooze.extensions, policy.max_extensions
+                ));
+            }
+            if !snooze.is_active(self.clock.today) {
+                text.push_str(", lapsed");
+            }
+            details.push(("snoozed", text));
+        }
+        details.push(("mode", self.mode.to_string()));
+        details.push(("clock", self.clock.to_string()));
+        Ok(details)
+    }
+
+    /// The report record of an annotation of `subject` with `outcome`.
+    pub fn record<'a>(
+        &self,
+        subject: &'a Subject,
+        args: &'a BestBeforeArgs,
+        outcome: &'a Outcome<'_>,
+    ) -> report::Record<'a> {
+        report::Record {
+            krate: subject.krate.as_deref(),
+            item: &subject.name,
+            path: subject.path.as_deref(),
+            location: subject.location.as_ref(),
+            args,
+            ticket: args
+                .ticket
+                .as_ref()
+                .map(|ticket| self.tickets.link(&ticket.value())),
+            state: outcome.verdict.state(),
+            message: outcome.verdict.message(),
+            snoozed_until: outcome
+                .snooze
+                .filter(|snooze| snooze.is_active(self.clock.today))
+                .map(|snooze| snooze.until.to_string()),
+            mode: self.mode.key(),
+            today: self.clock.today,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const SNOOZED: &str = r#"
+        [policy]
+        max_snooze = "6 months"
+        max_extensions = 2
+
+        [[snooze]]
+        id = "fees"
+        until = "2030-06"
+        reason = "Blocked on the billing migration"
+        approver = "alice"
+        extensions = 1
+    "#;
+
+    /// The outcome of an annotation with `args` in `mode` on the day `today`, as details and
+    /// verdict.
+    fn assess(
+        mode: Mode,
+        args: &str,
+        today: &str,
+    ) -> syn::Result<(Vec<(&'static str, String)>, Verdict)> {
+        let config = Config::parse(Path::new("bestbefore.toml"), SNOOZED)
+            .unwrap()
+            .unwrap();
+        let context = Context {
+            clock: Clock::fixed("test", today).unwrap(),
+            tickets: TicketConfig::resolve(&config).unwrap(),
+            mode,
+            config,
+        };
+        let mut args = syn::parse_str(args).unwrap();
+        let subject = Subject {
+            name: "fee".to_string(),
+            krate: None,
+            path: None,
+            location: None,
+        };
+        let outcome = context.assess(&mut args, &subject)?;
+        Ok((outcome.details, outcome.verdict))
+    }
+
+    /// The verdict on an annotation with `args` in `mode` on the day `today`.
+    fn verdict(mode: Mode, args: &str, today: &str) -> Verdict {
+        assess(mode, args, today).unwrap().1
+    }
+
+    #[test]
+    fn strict_mode_honors_snoozes() {
+        let snoozed = r#"expires = "2030-01", id = "fees""#;
+        assert!(matches!(
+            verdict(Mode::Strict, snoozed, "2030-03"),
+            Verdict::Warn { loud: true, .. }
+        ));
+        assert!(matches!(
+            verdict(Mode::Normal, snoozed, "2030-03"),
+            Verdict::Warn { loud: true, .. }
+        ));
+        // Lapsed snoozes and code without one fail as usual.
+        assert!(matches!(
+            verdict(Mode::Strict, snoozed, "2031-01"),
+            Verdict::Fail { .. }
+        ));
+        assert!(matches!(
+            verdict(Mode::Strict, r#""2030-01""#, "2030-03"),
+            Verdict::Fail { .. }
+        ));
+        assert!(matches!(
+            verdict(Mode::Off, snoozed, "2030-03"),
+            Verdict::Fresh
+        ));
+    }
+
+    #[test]
+    fn snoozes_count_from_the_deadline_of_the_annotation() {
+        let (details, _) = assess(
+            Mode::Normal,
+            r#"expires = "2030-01", id = "fees""#,
+            "2030-03",
+        )
+        .unwrap();
+        assert!(details.contains(&(
+            "snoozed",
+            "until 06.2030 by alice (Blocked on the billing migration), extension 1 of 2"
+                .to_string()
+        )));
+        // Six months after December 2029 is the end of June 2030, far enough.
+        assert!(assess(
+            Mode::Normal,
+            r#"expires = "2029-12", id = "fees""#,
+            "2030-03"
+        )
+        .is_ok());
+        let err = assess(
+            Mode::Normal,
+            r#"expires = "2029-11", id = "fees""#,
+            "2030-03",
+        )
+        .err()
+        .unwrap();
+        assert!(
+            err.to_string()
+                .starts_with("The snooze of id 'fees' until 06.2030 goes past the limit"),
+            "{}",
+            err
+        );
+    }
+}
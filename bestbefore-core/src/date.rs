@@ -0,0 +1,583 @@
+use chrono::{Datelike, NaiveDate, Weekday};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Range;
+
+/// The granularity a date was written in.
+///
+/// A date always denotes a whole period (a day, an ISO week, a month or a quarter).
+/// A threshold is considered passed once the current day lies after the last day of
+/// that period, so "03.2024" triggers from the 1st of April 2024 onwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Day,
+    Week,
+    Month,
+    Quarter,
+}
+
+/// A normalized date as accepted by the macro arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BestBeforeDate {
+    granularity: Granularity,
+    start: NaiveDate,
+    end: NaiveDate,
+}
+
+/// A date that could not be parsed.
+///
+/// `range` is the byte range of the offending part within the parsed string, so callers can
+/// point the diagnostic at exactly that part of the literal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateError {
+    pub message: String,
+    pub range: Range<usize>,
+}
+
+impl DateError {
+    fn new(range: Range<usize>, message: String) -> Self {
+        DateError { message, range }
+    }
+
+    /// Appends a "did you mean" hint built by replacing `range` of `input` with `replacement`.
+    fn suggest(mut self, input: &str, range: Range<usize>, replacement: &str) -> Self {
+        let mut fixed = String::with_capacity(input.len() + replacement.len());
+        fixed.push_str(&input[..range.start]);
+        fixed.push_str(replacement);
+        fixed.push_str(&input[range.end..]);
+        self.message = format!("{}; did you mean {}?", self.message, fixed);
+        self
+    }
+}
+
+/// A part of the input string together with its byte offset.
+struct Part<'a> {
+    text: &'a str,
+    start: usize,
+}
+
+impl Part<'_> {
+    fn range(&self) -> Range<usize> {
+        self.start..self.start + self.text.len()
+    }
+
+    fn skip(&self, n: usize) -> Self {
+        Part {
+            text: &self.text[n..],
+            start: self.start + n,
+        }
+    }
+}
+
+fn split(input: &str, separator: char) -> Vec<Part<'_>> {
+    let mut start = 0;
+    input
+        .split(separator)
+        .map(|text| {
+            let part = Part { text, start };
+            start += text.len() + separator.len_utf8();
+            part
+        })
+        .collect()
+}
+
+impl BestBeforeDate {
+    /// Parses any of the supported formats:
+    ///
+    /// - "MM.YYYY" (e.g. "03.2024")
+    /// - "YYYY-MM-DD" (e.g. "2024-03-15")
+    /// - "YYYY-MM" (e.g. "2024-03")
+    /// - "YYYY-Www" ISO week (e.g. "2024-W11")
+    /// - "Qn.YYYY" or "YYYY-Qn" quarter (e.g. "Q1.2024", "2024-Q1")
+    pub fn parse(input: &str) -> Result<Self, DateError> {
+        let dotted = split(input, '.');
+        if let [first, year] = dotted.as_slice() {
+            let year = parse_year(input, year)?;
+            return if first.text.starts_with(['Q', 'q']) {
+                Self::quarter(input, year, &first.skip(1))
+            } else {
+                Self::month(input, year, first)
+            };
+        }
+
+        let dashed = split(input, '-');
+        match dashed.as_slice() {
+            [year, month, day] if dotted.len() == 1 => {
+                let year = parse_year(input, year)?;
+                let month_value = parse_number(month, "month")?;
+                check_month(input, month, month_value)?;
+                Self::day(input, year, month_value, day)
+            }
+            [year, rest] if dotted.len() == 1 => {
+                let year = parse_year(input, year)?;
+                if rest.text.starts_with(['W', 'w']) {
+                    Self::week(input, year, &rest.skip(1))
+                } else if rest.text.starts_with(['Q', 'q']) {
+                    Self::quarter(input, year, &rest.skip(1))
+                } else {
+                    Self::month(input, year, rest)
+                }
+            }
+            _ => Err(format_error(input)),
+        }
+    }
+
+    fn day(input: &str, year: i32, month: u32, part: &Part) -> Result<Self, DateError> {
+        let day = parse_number(part, "day")?;
+        let first = NaiveDate::from_ymd_opt(year, month, 1)
+            .ok_or_else(|| DateError::new(0..input.len(), format!("Invalid date: '{}'", input)))?;
+        let last = last_day_of_month(first);
+        if day == 0 || day > last.day() {
+            let fixed = format!("{:02}", day.clamp(1, last.day()));
+            return Err(DateError::new(
+                part.range(),
+                format!("{} is not a day of {}", day, first.format("%Y-%m")),
+            )
+            .suggest(input, part.range(), &fixed));
+        }
+        let date = first.with_day(day).unwrap();
+        Ok(BestBeforeDate {
+            granularity: Granularity::Day,
+            start: date,
+            end: date,
+        })
+    }
+
+    fn week(input: &str, year: i32, part: &Part) -> Result<Self, DateError> {
+        let week = parse_number(part, "week")?;
+        let weeks = weeks_in_year(year);
+        let start = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon)
+            .filter(|_| week >= 1 && week <= weeks)
+            .ok_or_else(|| {
+                let fixed = format!("{:02}", week.clamp(1, weeks));
+                DateError::new(
+                    part.range(),
+                    format!(
+                        "{} has no week {} (weeks run from 1 to {})",
+                        year, week, weeks
+                    ),
+                )
+                .suggest(input, part.range(), &fixed)
+            })?;
+        Ok(BestBeforeDate {
+            granularity: Granularity::Week,
+            start,
+            end: start + chrono::Duration::days(6),
+        })
+    }
+
+    fn month(input: &str, year: i32, part: &Part) -> Result<Self, DateError> {
+        let month = parse_number(part, "month")?;
+        check_month(input, part, month)?;
+        let start = NaiveDate::from_ymd_opt(year, month, 1)
+            .ok_or_else(|| DateError::new(0..input.len(), format!("Invalid date: '{}'", input)))?;
+        Ok(BestBeforeDate {
+            granularity: Granularity::Month,
+            start,
+            end: last_day_of_month(start),
+        })
+    }
+
+    fn quarter(input: &str, year: i32, part: &Part) -> Result<Self, DateError> {
+        let quarter = parse_number(part, "quarter")?;
+        if !(1..=4).contains(&quarter) {
+            let fixed = quarter.clamp(1, 4).to_string();
+            return Err(
+                DateError::new(part.range(), format!("{} is not a quarter", quarter)).suggest(
+                    input,
+                    part.range(),
+                    &fixed,
+                ),
+            );
+        }
+        let start = NaiveDate::from_ymd_opt(year, (quarter - 1) * 3 + 1, 1)
+            .ok_or_else(|| DateError::new(0..input.len(), format!("Invalid date: '{}'", input)))?;
+        let last_month = NaiveDate::from_ymd_opt(year, quarter * 3, 1).unwrap();
+        Ok(BestBeforeDate {
+            granularity: Granularity::Quarter,
+            start,
+            end: last_day_of_month(last_month),
+        })
+    }
+
+    /// First day of the period.
+    pub fn start(&self) -> NaiveDate {
+        self.start
+    }
+
+    /// The last day of the period.
+    pub fn end(&self) -> NaiveDate {
+        self.end
+    }
+
+    /// A single day.
+    pub fn on(day: NaiveDate) -> Self {
+        BestBeforeDate {
+            granularity: Granularity::Day,
+            start: day,
+            end: day,
+        }
+    }
+
+    /// Returns `true` once `today` lies after the last day of the period.
+    pub fn is_past(&self, today: NaiveDate) -> bool {
+        today > self.end
+    }
+}
+
+/// A length of time such as "90 days", "6 weeks" or "3 months".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Days(u32),
+    Months(u32),
+}
+
+impl Period {
+    /// Parses a number followed by "day(s)", "week(s)", "month(s)", "quarter(s)" or "year(s)".
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let invalid = || {
+            format!(
+                "'{}' is not a period. Expected a number followed by days, weeks, months, quarters or years, such as '3 months'",
+                input
+            )
+        };
+        let (amount, unit) = input.trim().split_once(' ').ok_or_else(invalid)?;
+        let amount: u32 = amount.parse().map_err(|_| invalid())?;
+        let (period, factor): (fn(u32) -> Period, u32) = match unit.trim().trim_end_matches('s') {
+            "day" => (Period::Days, 1),
+            "week" => (Period::Days, 7),
+            "month" => (Period::Months, 1),
+            "quarter" => (Period::Months, 3),
+            "year" => (Period::Months, 12),
+            _ => return Err(invalid()),
+        };
+        amount
+            .checked_mul(factor)
+            .map(period)
+            .ok_or_else(|| format!("'{}' is too long a period", input))
+    }
+
+    /// The day this period after `day`, if it can be represented.
+    pub fn after(self, day: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Period::Days(days) => day.checked_add_days(chrono::Days::new(days.into())),
+            Period::Months(months) => day.checked_add_months(chrono::Months::new(months)),
+        }
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (amount, unit) = match *self {
+            Period::Days(days) => (days, "day"),
+            Period::Months(months) => (months, "month"),
+        };
+        write!(
+            f,
+            "{} {}{}",
+            amount,
+            unit,
+            if amount == 1 { "" } else { "s" }
+        )
+    }
+}
+
+impl Ord for BestBeforeDate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.end
+            .cmp(&other.end)
+            .then_with(|| self.start.cmp(&other.start))
+    }
+}
+
+impl PartialOrd for BestBeforeDate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BestBeforeDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let start = self.start;
+        match self.granularity {
+            Granularity::Day => write!(f, "{}", start.format("%Y-%m-%d")),
+            Granularity::Week => {
+                let week = start.iso_week();
+                write!(f, "{:04}-W{:02}", week.year(), week.week())
+            }
+            Granularity::Month => write!(f, "{:02}.{:02}", start.month(), start.year()),
+            Granularity::Quarter => write!(f, "Q{}.{}", start.month0() / 3 + 1, start.year()),
+        }
+    }
+}
+
+const EXPECTED_FORMATS: &str =
+    "expected one of 'MM.YYYY', 'YYYY-MM-DD', 'YYYY-MM', 'YYYY-Www', 'Qn.YYYY' or 'YYYY-Qn'";
+
+fn format_error(input: &str) -> DateError {
+    let error = DateError::new(
+        0..input.len(),
+        format!("Invalid date format: '{}'; {}", input, EXPECTED_FORMATS),
+    );
+
+    // Common slips: other separators or surrounding whitespace.
+    let candidates = [
+        input.trim().to_string(),
+        input.trim().replace(['/', ' '], "."),
+        input.trim().replace(['/', ' ', '.'], "-"),
+    ];
+    match candidates
+        .iter()
+        .find(|candidate| candidate.as_str() != input && BestBeforeDate::parse(candidate).is_ok())
+    {
+        Some(candidate) => error.suggest(input, 0..input.len(), candidate),
+        None => error,
+    }
+}
+
+fn parse_number(part: &Part, what: &str) -> Result<u32, DateError> {
+    if part.text.is_empty() || !part.text.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(DateError::new(
+            part.range(),
+            format!("Invalid {}: '{}'. Expected a number", what, part.text),
+        ));
+    }
+    part.text.parse::<u32>().map_err(|_| {
+        DateError::new(
+            part.range(),
+            format!("Invalid {}: '{}'. Expected a number", what, part.text),
+        )
+    })
+}
+
+fn parse_year(input: &str, part: &Part) -> Result<i32, DateError> {
+    if part.text.len() == 2 && part.text.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(DateError::new(
+            part.range(),
+            format!("'{}' is not a four-digit year", part.text),
+        )
+        .suggest(input, part.range(), &format!("20{}", part.text)));
+    }
+    if part.text.len() != 4 || !part.text.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(DateError::new(
+            part.range(),
+            format!("Invalid year: '{}'. Expected a four-digit year", part.text),
+        ));
+    }
+    Ok(part.text.parse::<i32>().unwrap())
+}
+
+fn check_month(input: &str, part: &Part, month: u32) -> Result<(), DateError> {
+    if (1..=12).contains(&month) {
+        return Ok(());
+    }
+    let fixed = format!("{:0width$}", month.clamp(1, 12), width = part.text.len());
+    Err(
+        DateError::new(part.range(), format!("{} is not a month", month)).suggest(
+            input,
+            part.range(),
+            &fixed,
+        ),
+    )
+}
+
+fn last_day_of_month(first: NaiveDate) -> NaiveDate {
+    let (year, month) = if first.month() == 12 {
+        (first.year() + 1, 1)
+    } else {
+        (first.year(), first.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap() - chrono::Duration::days(1)
+}
+
+fn weeks_in_year(year: i32) -> u32 {
+    NaiveDate::from_ymd_opt(year, 12, 28)
+        .unwrap()
+        .iso_week()
+        .week()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn period(input: &str) -> (NaiveDate, NaiveDate) {
+        let date = BestBeforeDate::parse(input).unwrap();
+        (date.start(), date.end())
+    }
+
+    fn error(input: &str) -> DateError {
+        BestBeforeDate::parse(input).unwrap_err()
+    }
+
+    #[test]
+    fn months() {
+        assert_eq!(period("03.2024"), (day("2024-03-01"), day("2024-03-31")));
+        assert_eq!(period("2024-03"), (day("2024-03-01"), day("2024-03-31")));
+        assert_eq!(period("12.2024"), (day("2024-12-01"), day("2024-12-31")));
+        assert_eq!(period("02.2024").1, day("2024-02-29"));
+        assert_eq!(period("02.2023").1, day("2023-02-28"));
+    }
+
+    #[test]
+    fn days() {
+        assert_eq!(period("2024-03-15"), (day("2024-03-15"), day("2024-03-15")));
+        assert_eq!(period("2024-02-29").0, day("2024-02-29"));
+    }
+
+    #[test]
+    fn weeks() {
+        assert_eq!(period("2024-W01"), (day("2024-01-01"), day("2024-01-07")));
+        assert_eq!(period("2024-w11").0, day("2024-03-11"));
+        // 2020 has 53 ISO weeks, the last of which ends in 2021.
+        assert_eq!(period("2020-W53"), (day("2020-12-28"), day("2021-01-03")));
+        // Week 1 of 2025 starts in 2024.
+        assert_eq!(period("2025-W01").0, day("2024-12-30"));
+    }
+
+    #[test]
+    fn quarters() {
+        assert_eq!(period("Q1.2024"), (day("2024-01-01"), day("2024-03-31")));
+        assert_eq!(period("2024-Q1"), period("Q1.2024"));
+        assert_eq!(period("q4.2024"), (day("2024-10-01"), day("2024-12-31")));
+        assert_eq!(period("2024-Q3").1, day("2024-09-30"));
+    }
+
+    #[test]
+    fn thresholds_pass_after_the_period() {
+        let date = BestBeforeDate::parse("03.2024").unwrap();
+        assert!(!date.is_past(day("2024-03-31")));
+        assert!(date.is_past(day("2024-04-01")));
+        let date = BestBeforeDate::parse("2024-Q1").unwrap();
+        assert!(!date.is_past(day("2024-03-31")));
+        assert!(date.is_past(day("2024-04-01")));
+    }
+
+    #[test]
+    fn display_normalizes() {
+        let display = |input: &str| BestBeforeDate::parse(input).unwrap().to_string();
+        assert_eq!(display("2024-03"), "03.2024");
+        assert_eq!(display("2024-q2"), "Q2.2024");
+        assert_eq!(display("2024-w05"), "2024-W05");
+        assert_eq!(display("2024-03-05"), "2024-03-05");
+    }
+
+    #[test]
+    fn dates_order_by_their_last_day() {
+        let parse = |input: &str| BestBeforeDate::parse(input).unwrap();
+        assert!(parse("2024-03-15") < parse("03.2024"));
+        assert!(parse("03.2024") < parse("Q1.2024").max(parse("2024-04-01")));
+        assert!(parse("Q1.2024") > parse("02.2024"));
+    }
+
+    #[test]
+    fn invalid_days() {
+        let err = error("2023-02-29");
+        assert_eq!(
+            err.message,
+            "29 is not a day of 2023-02; did you mean 2023-02-28?"
+        );
+        assert_eq!(err.range, 8..10);
+        assert_eq!(
+            error("2024-04-00").message,
+            "0 is not a day of 2024-04; did you mean 2024-04-01?"
+        );
+    }
+
+    #[test]
+    fn invalid_weeks() {
+        let err = error("2021-W53");
+        assert_eq!(
+            err.message,
+            "2021 has no week 53 (weeks run from 1 to 52); did you mean 2021-W52?"
+        );
+        assert_eq!(err.range, 6..8);
+        assert_eq!(
+            error("2024-W00").message,
+            "2024 has no week 0 (weeks run from 1 to 52); did you mean 2024-W01?"
+        );
+    }
+
+    #[test]
+    fn invalid_months_and_quarters() {
+        let err = error("13.2024");
+        assert_eq!(err.message, "13 is not a month; did you mean 12.2024?");
+        assert_eq!(err.range, 0..2);
+        assert_eq!(
+            error("2024-00").message,
+            "0 is not a month; did you mean 2024-01?"
+        );
+        let err = error("Q5.2024");
+        assert_eq!(err.message, "5 is not a quarter; did you mean Q4.2024?");
+        assert_eq!(err.range, 1..2);
+        assert_eq!(
+            error("2024-Q0").message,
+            "0 is not a quarter; did you mean 2024-Q1?"
+        );
+    }
+
+    #[test]
+    fn invalid_years() {
+        let err = error("03.24");
+        assert_eq!(
+            err.message,
+            "'24' is not a four-digit year; did you mean 03.2024?"
+        );
+        assert_eq!(err.range, 3..5);
+        assert_eq!(
+            error("03.20x4").message,
+            "Invalid year: '20x4'. Expected a four-digit year"
+        );
+    }
+
+    #[test]
+    fn invalid_formats() {
+        let err = error("2024/03");
+        assert_eq!(err.range, 0..7);
+        assert_eq!(
+            err.message,
+            format!(
+                "Invalid date format: '2024/03'; {}; did you mean 2024-03?",
+                EXPECTED_FORMATS
+            )
+        );
+        assert!(error("03/2024")
+            .message
+            .ends_with("; did you mean 03.2024?"));
+        assert_eq!(
+            error("soon").message,
+            format!("Invalid date format: 'soon'; {}", EXPECTED_FORMATS)
+        );
+        assert_eq!(
+            error("xx.2024").message,
+            "Invalid month: 'xx'. Expected a number"
+        );
+    }
+
+    #[test]
+    fn periods() {
+        assert_eq!(Period::parse("90 days"), Ok(Period::Days(90)));
+        assert_eq!(Period::parse("1 day"), Ok(Period::Days(1)));
+        assert_eq!(Period::parse("6 weeks"), Ok(Period::Days(42)));
+        assert_eq!(Period::parse("3 months"), Ok(Period::Months(3)));
+        assert_eq!(Period::parse("2 quarters"), Ok(Period::Months(6)));
+        assert_eq!(Period::parse("1 year"), Ok(Period::Months(12)));
+        assert!(Period::parse("soon").is_err());
+        assert!(Period::parse("3 fortnights").is_err());
+        assert_eq!(
+            Period::parse("700000000 weeks"),
+            Err("'700000000 weeks' is too long a period".to_string())
+        );
+        assert!(Period::parse("400000000 years").is_err());
+        assert_eq!(
+            Period::Months(1).after(day("2024-01-31")),
+            Some(day("2024-02-29"))
+        );
+        assert_eq!(Period::Days(42).to_string(), "42 days");
+        assert_eq!(Period::Months(1).to_string(), "1 month");
+    }
+}
@@ -0,0 +1,24 @@
+//! The environment variables evaluation depends on.
+
+use std::env;
+
+/// Environment variables that influence the outcome of an evaluation.
+///
+/// The macro records them as inputs of the crate being compiled, so cargo recompiles it when one
+/// of them changes.
+pub const VARS: &[&str] = &[
+    "BESTBEFORE_DATE",
+    "SOURCE_DATE_EPOCH",
+    "BESTBEFORE_TZ",
+    "CI",
+    "BESTBEFORE_TICKET_URL",
+    "BESTBEFORE_TICKET_PATTERN",
+    "BESTBEFORE_MODE",
+    "BESTBEFORE_REPORT",
+];
+
+/// Reads one of [`VARS`].
+pub fn var(name: &str) -> Result<String, env::VarError> {
+    debug_assert!(VARS.contains(&name), "{} is not a tracked variable", name);
+    env::var(name)
+}
@@ -0,0 +1,134 @@
+use crate::args::{BestBeforeArgs, SpannedDate, Stage};
+use crate::config::{self, Config};
+use chrono::NaiveDate;
+
+/// The outcome of evaluating an annotation on a given day.
+pub enum Verdict {
+    /// No stage has been reached yet.
+    Fresh,
+    /// The `note` stage: documented, but no diagnostic.
+    Note { message: String },
+    /// The `warn` stage, or `deny_in_ci` outside of CI. A `loud` warning is reported at the
+    /// definition as well as at uses.
+    Warn {
+        date: SpannedDate,
+        message: String,
+        loud: bool,
+    },
+    /// The `error` stage, or `deny_in_ci` under CI.
+    Fail { date: SpannedDate, message: String },
+}
+
+impl Verdict {
+    /// The state as recorded in reports: "fresh", "note", "warning" or "error".
+    pub fn state(&self) -> &'static str {
+        match self {
+            Verdict::Fresh => "fresh",
+            Verdict::Note { .. } => "note",
+            Verdict::Warn { .. } => "warning",
+            Verdict::Fail { .. } => "error",
+        }
+    }
+
+    /// The message of the diagnostic or note, if any.
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            Verdict::Fresh => None,
+            Verdict::Note { message }
+            | Verdict::Warn { message, .. }
+            | Verdict::Fail { message, .. } => Some(message),
+        }
+    }
+}
+
+/// Evaluates `args` for the code named `item_name` on `today`.
+///
+/// Messages given by the annotation take precedence over the templates of `config`, which take
+/// precedence over the built-in messages.
+pub fn evaluate(
+    args: &BestBeforeArgs,
+    item_name: &str,
+    today: NaiveDate,
+    in_ci: bool,
+    config: &Config,
+) -> Verdict {
+    let Some(active) = args.active_stage(today) else {
+        return Verdict::Fresh;
+    };
+    let date = active.date;
+
+    let custom = if active.stage == Stage::Error {
+        &args.expire_message
+    } else {
+        &args.warn_message
+    };
+    let message = custom.clone().or_else(|| {
+        config.message(active.stage).map(|template| {
+            config::render(
+                template,
+                &[
+                    ("item", item_name),
+                    ("date", &date.value.to_string()),
+                    ("owner", args.owner.as_deref().unwrap_or("unassigned")),
+                    (
+                        "ticket",
+                        args.ticket
+                            .as_ref()
+                            .map(|ticket| ticket.value())
+                            .as_deref()
+                            .unwrap_or("none"),
+                    ),
+                ],
+            )
+        })
+    });
+
+    match active.stage {
+        Stage::Error => Verdict::Fail {
+            date,
+            message: message.unwrap_or_else(|| {
+                format!(
+                    "Code '{}' has expired (after {}): consider removing this code",
+                    item_name, date.value
+                )
+            }),
+        },
+        Stage::DenyInCi if in_ci => Verdict::Fail {
+            date,
+            message: message.clone().unwrap_or_else(|| {
+                format!(
+                    "Code '{}' past deny-in-CI date ({}): CI builds fail until this code is updated or removed",
+                    item_name, date.value
+                )
+            }),
+        },
+        Stage::DenyInCi => Verdict::Warn {
+            date,
+            loud: false,
+            message: message.clone().unwrap_or_else(|| {
+                format!(
+                    "Code '{}' past deny-in-CI date ({}): CI builds now fail on this code, consider updating or removing it",
+                    item_name, date.value
+                )
+            }),
+        },
+        Stage::Warn => Verdict::Warn {
+            date,
+            loud: false,
+            message: message.clone().unwrap_or_else(|| {
+                format!(
+                    "Code '{}' past warning date ({}): consider updating or removing this code",
+                    item_name, date.value
+                )
+            }),
+        },
+        Stage::Note => Verdict::Note {
+            message: message.clone().unwrap_or_else(|| {
+                format!(
+                    "Code '{}' past note date ({}): scheduled for updating or removal",
+                    item_name, date.value
+                )
+            }),
+        },
+    }
+}
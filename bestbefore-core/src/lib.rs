@@ -0,0 +1,17 @@
+//! The evaluation of `#[bestbefore]` annotations, shared by the `bestbefore` macro and the
+//! `cargo-bestbefore` command so both report exactly the same.
+//!
+//! This crate is an implementation detail of those two and has no stable API of its own.
+
+pub mod args;
+pub mod clock;
+pub mod config;
+pub mod context;
+pub mod date;
+pub mod environment;
+pub mod eval;
+pub mod location;
+pub mod mode;
+pub mod report;
+pub mod target;
+pub mod ticket;
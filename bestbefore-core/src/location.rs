@@ -0,0 +1,300 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Where an annotation is in the source.
+#[derive(Debug, Clone)]
+pub struct Location {
+    /// The source file, relative to the package root where possible.
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    /// The modules declared inline around the annotation, outermost first, as found by
+    /// [`inline_modules`].
+    pub modules: Vec<String>,
+}
+
+impl Location {
+    /// The path of the module the annotation is in, such as `["legacy", "fees", "tests"]` for
+    /// `mod tests` in `src/legacy/fees.rs`.
+    pub fn module_path(&self) -> Vec<String> {
+        let mut path = module_path(&self.file);
+        path.extend(self.modules.iter().cloned());
+        path
+    }
+}
+
+/// The path of an item named `name` at `location`, such as `billing::legacy::fee`.
+///
+/// The path starts with the name of the crate, if known.
+pub fn item_path(krate: Option<&str>, location: &Location, name: &str) -> String {
+    let mut segments = Vec::new();
+    if let Some(krate) = krate {
+        segments.push(krate.to_string());
+    }
+    segments.extend(location.module_path());
+    segments.push(name.to_string());
+    segments.join("::")
+}
+
+/// The module path for a source file relative to the package root.
+fn module_path(file: &Path) -> Vec<String> {
+    let mut parts: Vec<String> = file
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => part.to_str().map(str::to_string),
+            _ => None,
+        })
+        .collect();
+    // Only files below src/ form modules; tests, examples and benches are crate roots.
+    if parts.first().map(String::as_str) != Some("src") {
+        return Vec::new();
+    }
+    parts.remove(0);
+    if parts.first().map(String::as_str) == Some("bin") {
+        return Vec::new();
+    }
+    if let Some(last) = parts.pop() {
+        let stem = last.strip_suffix(".rs").unwrap_or(&last);
+        let crate_root = parts.is_empty() && (stem == "lib" || stem == "main");
+        if stem != "mod" && !crate_root {
+            parts.push(stem.to_string());
+        }
+    }
+    parts
+}
+
+/// The names of the `mod name { ... }` blocks around `line` and `column` (both 1-based) of
+/// `source`, outermost first.
+///
+/// The macro only sees the annotated item, so it reads the enclosing modules from the file, and
+/// `cargo-bestbefore` does the same to arrive at the same paths. Comments and literals are
+/// skipped; modules written by macros are not seen.
+pub fn inline_modules(source: &str, line: usize, column: usize) -> Vec<String> {
+    let chars = source.chars().collect::<Vec<_>>();
+    // The open modules, with the brace depth inside them.
+    let mut modules: Vec<(String, usize)> = Vec::new();
+    let mut depth = 0;
+    // Set after `mod`, then to the module name until its body opens.
+    let mut after_mod = false;
+    let mut pending = None;
+    let (mut index, mut at) = (0, (1, 1));
+    let advance = |index: &mut usize, at: &mut (usize, usize), count: usize| {
+        for c in &chars[*index..(*index + count).min(chars.len())] {
+            *at = if *c == '\n' {
+                (at.0 + 1, 1)
+            } else {
+                (at.0, at.1 + 1)
+            };
+        }
+        *index = (*index + count).min(chars.len());
+    };
+    let next = |index: usize, offset: usize| chars.get(index + offset).copied();
+
+    while index < chars.len() && at < (line, column) {
+        let c = chars[index];
+        if c.is_whitespace() {
+            advance(&mut index, &mut at, 1);
+            continue;
+        }
+        let length = match (c, next(index, 1)) {
+            ('/', Some('/')) => chars[index..]
+                .iter()
+                .position(|&c| c == '\n')
+                .unwrap_or(chars.len() - index),
+            ('/', Some('*')) => block_comment(&chars[index..]),
+            ('"', _) => quoted(&chars[index..], '"'),
+            ('\'', _) => {
+                // A character literal, or else a lifetime or label.
+                match (next(index, 1), next(index, 2)) {
+                    (Some('\\'), _) | (Some(_), Some('\'')) => quoted(&chars[index..], '\''),
+                    _ => 1 + identifier(&chars[index + 1..]),
+                }
+            }
+            (c, _) if c.is_alphanumeric() || c == '_' => {
+                let length = identifier(&chars[index..]);
+                let word = chars[index..index + length].iter().collect::<String>();
+                match (word.as_str(), next(index, length)) {
+                    ("r" | "br" | "cr", Some('"' | '#')) => {
+                        length + raw_string(&chars[index + length..])
+                    }
+                    ("b" | "c", Some('"')) => length + quoted(&chars[index + length..], '"'),
+                    ("b", Some('\'')) => length + quoted(&chars[index + length..], '\''),
+                    _ => {
+                        if after_mod {
+                            pending = Some(word.trim_start_matches("r#").to_string());
+                        }
+                        after_mod = word == "mod";
+                        advance(&mut index, &mut at, length);
+                        continue;
+                    }
+                }
+            }
+            ('{', _) => {
+                depth += 1;
+                if let Some(name) = pending.take() {
+                    modules.push((name, depth));
+                }
+                1
+            }
+            ('}', _) => {
+                if modules.last().is_some_and(|(_, inside)| *inside == depth) {
+                    modules.pop();
+                }
+                depth = depth.saturating_sub(1);
+                1
+            }
+            _ => 1,
+        };
+        after_mod = false;
+        pending = None;
+        advance(&mut index, &mut at, length.max(1));
+    }
+    modules.into_iter().map(|(name, _)| name).collect()
+}
+
+/// The length of the identifier, keyword or number at the start of `chars`, including a `r#`
+/// prefix.
+fn identifier(chars: &[char]) -> usize {
+    let raw = chars.starts_with(&['r', '#'])
+        && chars.get(2).is_some_and(|c| c.is_alphabetic() || *c == '_');
+    let prefix = if raw { 2 } else { 0 };
+    prefix
+        + chars[prefix..]
+            .iter()
+            .take_while(|c| c.is_alphanumeric() || **c == '_')
+            .count()
+}
+
+/// The length of the literal quoted with `quote` at the start of `chars`, with escapes.
+fn quoted(chars: &[char], quote: char) -> usize {
+    let mut escaped = false;
+    for (index, &c) in chars.iter().enumerate().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == quote => return index + 1,
+            _ => {}
+        }
+    }
+    chars.len()
+}
+
+/// The length of the raw string at the start of `chars`, after its `r` prefix.
+fn raw_string(chars: &[char]) -> usize {
+    let hashes = chars.iter().take_while(|&&c| c == '#').count();
+    if chars.get(hashes) != Some(&'"') {
+        // A raw identifier such as `r#mod`.
+        return 0;
+    }
+    let end = std::iter::once('"')
+        .chain(std::iter::repeat_n('#', hashes))
+        .collect::<Vec<_>>();
+    chars[hashes + 1..]
+        .windows(end.len())
+        .position(|window| window == end.as_slice())
+        .map_or(chars.len(), |position| hashes + 1 + position + end.len())
+}
+
+/// The length of the block comment at the start of `chars`, which may be nested.
+fn block_comment(chars: &[char]) -> usize {
+    let mut depth = 0;
+    let mut index = 0;
+    while index + 1 < chars.len() {
+        match (chars[index], chars[index + 1]) {
+            ('/', '*') => {
+                depth += 1;
+                index += 2;
+            }
+            ('*', '/') => {
+                depth -= 1;
+                index += 2;
+                if depth == 0 {
+                    return index;
+                }
+            }
+            _ => index += 1,
+        }
+    }
+    chars.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modules_at(source: &str, marker: &str) -> Vec<String> {
+        let offset = source.find(marker).expect("marker not found");
+        let before = &source[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before[before.rfind('\n').map_or(0, |i| i + 1)..]
+            .chars()
+            .count()
+            + 1;
+        inline_modules(source, line, column)
+    }
+
+    #[test]
+    fn nested_modules() {
+        let source = "
+            mod outer {
+                fn before() {}
+                pub(crate) mod r#inner {
+                    fn HERE() {}
+                }
+                fn AFTER() {}
+            }
+            fn OUTSIDE() {}
+        ";
+        assert_eq!(modules_at(source, "HERE"), ["outer", "inner"]);
+        assert_eq!(modules_at(source, "AFTER"), ["outer"]);
+        assert!(modules_at(source, "OUTSIDE").is_empty());
+    }
+
+    #[test]
+    fn modules_declared_elsewhere_and_other_blocks() {
+        let source = "
+            mod legacy;
+            impl Fee { fn new() {} }
+            fn f() { struct S { mod_like: u8 } }
+            fn HERE() {}
+        ";
+        assert!(modules_at(source, "HERE").is_empty());
+    }
+
+    #[test]
+    fn braces_in_comments_and_literals() {
+        let source = r####"
+            mod fees {
+                // mod commented { }
+                /* mod nested /* } */ { */
+                const A: &str = "} \" }";
+                const B: &str = r##"mod raw { "# }"##;
+                const C: &[u8] = br##"}"##;
+                const D: char = '}';
+                const E: u8 = b'{';
+                fn lifetime<'a>(s: &'a str) -> &'a str { 'label: loop { break 'label s; } }
+                fn HERE() {}
+            }
+        "####;
+        assert_eq!(modules_at(source, "HERE"), ["fees"]);
+    }
+
+    #[test]
+    fn columns_count_characters() {
+        let source = "mod ä { const S: &str = \"é\"; fn HERE() {} }";
+        assert_eq!(modules_at(source, "HERE"), ["ä"]);
+    }
+
+    #[test]
+    fn item_paths_include_inline_modules() {
+        let location = Location {
+            file: PathBuf::from("src/legacy/mod.rs"),
+            line: 3,
+            column: 5,
+            modules: vec!["export".to_string()],
+        };
+        assert_eq!(
+            item_path(Some("billing"), &location, "new"),
+            "billing::legacy::export::new"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::config::Config;
+use crate::environment;
+use crate::eval::Verdict;
+use std::fmt;
+
+/// How strictly annotations are enforced, for the whole build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// No diagnostics at all.
+    Off,
+    /// Expired code produces a warning instead of an error.
+    WarnOnly,
+    /// Warnings and errors as configured by the annotations.
+    #[default]
+    Normal,
+    /// Warnings are errors, except for snoozed code.
+    Strict,
+}
+
+impl Mode {
+    const ALL: [Mode; 4] = [Mode::Off, Mode::WarnOnly, Mode::Normal, Mode::Strict];
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        Mode::ALL
+            .into_iter()
+            .find(|mode| mode.key() == value.trim())
+            .ok_or_else(|| {
+                format!(
+                    "'{}' is not a mode, expected 'off', 'warn-only', 'normal' or 'strict'",
+                    value
+                )
+            })
+    }
+
+    /// The name of the mode, as given in `BESTBEFORE_MODE`.
+    pub fn key(self) -> &'static str {
+        match self {
+            Mode::Off => "off",
+            Mode::WarnOnly => "warn-only",
+            Mode::Normal => "normal",
+            Mode::Strict => "strict",
+        }
+    }
+
+    /// The mode from `BESTBEFORE_MODE`, or else from the configuration.
+    pub fn resolve(config: &Config) -> Result<Self, String> {
+        Mode::from_var(environment::var("BESTBEFORE_MODE").ok(), config)
+    }
+
+    /// The mode from `value`, the value of `BESTBEFORE_MODE`, or else from the configuration.
+    fn from_var(value: Option<String>, config: &Config) -> Result<Self, String> {
+        match value {
+            Some(value) => Mode::parse(&value)
+                .map_err(|err| format!("Invalid BESTBEFORE_MODE environment variable: {}", err)),
+            None => Ok(config.mode.unwrap_or_default()),
+        }
+    }
+
+    /// Adjusts a verdict reached by the annotation's own rules to this mode.
+    pub fn enforce(self, verdict: Verdict) -> Verdict {
+        match (self, verdict) {
+            (Mode::Off, _) => Verdict::Fresh,
+            (Mode::WarnOnly, Verdict::Fail { date, message }) => Verdict::Warn {
+                date,
+                message,
+                loud: true,
+            },
+            (Mode::Strict, Verdict::Warn { date, message, .. }) => Verdict::Fail { date, message },
+            (_, verdict) => verdict,
+        }
+    }
+}
+
+/// Returns `true` when running under a CI system, which conventionally sets `CI`.
+pub fn running_in_ci() -> bool {
+    is_ci(environment::var("CI").ok().as_deref())
+}
+
+/// Whether `value` of `CI` means running under CI: anything but nothing, `false` or `0`.
+fn is_ci(value: Option<&str>) -> bool {
+    value.is_some_and(|value| !value.is_empty() && value != "false" && value != "0")
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Off => write!(f, "off"),
+            Mode::WarnOnly => write!(f, "warn-only (expired code only warns)"),
+            Mode::Normal => write!(f, "normal"),
+            Mode::Strict => write!(f, "strict (warnings are errors)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes() {
+        assert_eq!(Mode::parse("off"), Ok(Mode::Off));
+        assert_eq!(Mode::parse("warn-only"), Ok(Mode::WarnOnly));
+        assert_eq!(Mode::parse(" normal\n"), Ok(Mode::Normal));
+        assert_eq!(Mode::parse("strict"), Ok(Mode::Strict));
+        for mode in Mode::ALL {
+            assert_eq!(Mode::parse(mode.key()), Ok(mode));
+        }
+        assert_eq!(
+            Mode::parse("Strict"),
+            Err(
+                "'Strict' is not a mode, expected 'off', 'warn-only', 'normal' or 'strict'"
+                    .to_string()
+            )
+        );
+        assert!(Mode::parse("warn_only").is_err());
+        assert!(Mode::parse("").is_err());
+    }
+
+    #[test]
+    fn the_environment_overrides_the_configuration() {
+        let config = Config {
+            mode: Some(Mode::Strict),
+            ..Config::default()
+        };
+        assert_eq!(Mode::from_var(None, &config), Ok(Mode::Strict));
+        assert_eq!(Mode::from_var(None, &Config::default()), Ok(Mode::Normal));
+        assert_eq!(
+            Mode::from_var(Some("off".to_string()), &config),
+            Ok(Mode::Off)
+        );
+        let err = Mode::from_var(Some("lenient".to_string()), &config).unwrap_err();
+        assert!(
+            err.starts_with("Invalid BESTBEFORE_MODE environment variable: 'lenient'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn ci_detection() {
+        for value in ["true", "1", "yes", "github"] {
+            assert!(is_ci(Some(value)), "{}", value);
+        }
+        for value in [None, Some(""), Some("false"), Some("0")] {
+            assert!(!is_ci(value), "{:?}", value);
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! A JSON Lines report of every evaluated annotation.
+//!
+//! Each expansion appends one line per annotation to the report file. The file is locked while a
+//! line is written, so the compiler can run any number of crates in parallel. The format is
+//! described in the crate documentation and identified by [`SCHEMA`] in every record.
+
+use crate::args::BestBeforeArgs;
+use crate::config::Config;
+use crate::environment;
+use crate::location::Location;
+use chrono::NaiveDate;
+use std::env;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Identifies the format of the records. Bumped on any incompatible change.
+pub const SCHEMA: &str = "bestbefore-report/1";
+
+/// Where the report is written: `BESTBEFORE_REPORT`, or else `report` in the configuration.
+///
+/// A relative path in the environment variable is taken relative to the working directory of
+/// the compiler, which is the workspace root under cargo. One in the configuration is taken
+/// relative to the configuration file.
+pub fn path(config: &Config) -> Option<PathBuf> {
+    if let Ok(path) = environment::var("BESTBEFORE_REPORT") {
+        if path.is_empty() {
+            return None;
+        }
+        let path = PathBuf::from(path);
+        return Some(match env::current_dir() {
+            Ok(dir) if path.is_relative() => dir.join(path),
+            _ => path,
+        });
+    }
+    let report = config.report.as_ref()?;
+    Some(match config.path.as_deref().and_then(Path::parent) {
+        Some(dir) if report.is_relative() => dir.join(report),
+        _ => report.clone(),
+    })
+}
+
+/// The record of one annotation.
+pub struct Record<'a> {
+    pub krate: Option<&'a str>,
+    pub item: &'a str,
+    pub path: Option<&'a str>,
+    pub location: Option<&'a Location>,
+    pub args: &'a BestBeforeArgs,
+    pub ticket: Option<String>,
+    pub state: &'a str,
+    pub message: Option<&'a str>,
+    pub snoozed_until: Option<String>,
+    pub mode: &'a str,
+    pub today: NaiveDate,
+}
+
+impl Record<'_> {
+    /// Renders the record as a single line of JSON.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{");
+        let mut field = |name: &str, value: String| {
+            if json.len() > 1 {
+                json.push(',');
+            }
+            let _ = write!(json, "{}:{}", string(name), value);
+        };
+
+        field("schema", string(SCHEMA));
+        field("crate", optional(self.krate));
+        field("item", string(self.item));
+        field("path", optional(self.path));
+        field("id", optional(self.args.id.as_deref()));
+        field(
+            "file",
+            optional(
+                self.location
+                    .map(|location| location.file.to_string_lossy().replace('\\', "/"))
+                    .as_deref(),
+            ),
+        );
+        field(
+            "line",
+            self.location
+                .map_or("null".to_string(), |location| location.line.to_string()),
+        );
+        field(
+            "column",
+            self.location
+                .map_or("null".to_string(), |location| location.column.to_string()),
+        );
+        let dates = self
+            .args
+            .stages
+            .iter()
+            .map(|stage| {
+                format!(
+                    "{{\"stage\":{},\"date\":{},\"last_day\":{}}}",
+                    string(stage.stage.key()),
+                    string(&stage.date.value.to_string()),
+                    string(&stage.date.value.end().format("%Y-%m-%d").to_string())
+                )
+            })
+            .collect::<Vec<_>>();
+        field("dates", format!("[{}]", dates.join(",")));
+        field("state", string(self.state));
+        field(
+            "stage",
+            optional(
+                self.args
+                    .active_stage(self.today)
+                    .map(|stage| stage.stage.key()),
+            ),
+        );
+        field("message", optional(self.message));
+        field("owner", optional(self.args.owner.as_deref()));
+        field("ticket", optional(self.ticket.as_deref()));
+        field(
+            "replacement",
+            optional(
+                self.args
+                    .replacement
+                    .as_ref()
+                    .map(crate::target::render_tokens)
+                    .as_deref(),
+            ),
+        );
+        field("snoozed_until", optional(self.snoozed_until.as_deref()));
+        field("mode", string(self.mode));
+        field("today", string(&self.today.format("%Y-%m-%d").to_string()));
+
+        json.push('}');
+        json
+    }
+}
+
+/// Appends `line` to the report at `path`, creating the file and its directory as needed.
+pub fn append(path: &Path, line: &str) -> Result<(), String> {
+    let error = |err: std::io::Error| format!("Cannot write report {}: {}", path.display(), err);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(error)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(error)?;
+    file.lock().map_err(error)?;
+    let mut record = String::with_capacity(line.len() + 1);
+    record.push_str(line);
+    record.push('\n');
+    let written = file.write_all(record.as_bytes());
+    let _ = file.unlock();
+    written.map_err(error)
+}
+
+/// A JSON string literal.
+pub fn string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn optional(value: Option<&str>) -> String {
+    value.map_or("null".to_string(), string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_escape_quotes_and_backslashes() {
+        assert_eq!(string(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(string(r"C:\rates"), r#""C:\\rates""#);
+        assert_eq!(string(""), r#""""#);
+    }
+
+    #[test]
+    fn strings_escape_control_characters() {
+        assert_eq!(string("a\nb\r\tc"), r#""a\nb\r\tc""#);
+        assert_eq!(string("\0\u{1b}[0m\u{1f}"), r#""\u0000\u001b[0m\u001f""#);
+        // DEL is not a control character in JSON.
+        assert_eq!(string("\u{7f}"), "\"\u{7f}\"");
+    }
+
+    #[test]
+    fn strings_keep_non_ascii_characters() {
+        assert_eq!(string("Größe €"), "\"Größe €\"");
+        assert_eq!(string("🦀\u{2028}"), "\"🦀\u{2028}\"");
+    }
+
+    #[test]
+    fn records_escape_their_fields() {
+        let args: BestBeforeArgs = syn::parse_str(r#""2025-06", owner = "R&D \"rates\"""#).unwrap();
+        let record = Record {
+            krate: Some("billing"),
+            item: "rate",
+            path: None,
+            location: None,
+            args: &args,
+            ticket: None,
+            state: "warn",
+            message: Some("Replace\tthe \"tiers\", see C:\\rates"),
+            snoozed_until: None,
+            mode: "local",
+            today: NaiveDate::from_ymd_opt(2025, 7, 1).unwrap(),
+        };
+        let json = record.to_json();
+        assert!(json.starts_with(r#"{"schema":"bestbefore-report/1","crate":"billing","#));
+        assert!(
+            json.contains(
+                r#""message":"Replace\tthe \"tiers\", see C:\\rates","owner":"R&D \"rates\"""#
+            ),
+            "{}",
+            json
+        );
+        assert!(
+            json.contains(r#""file":null,"line":null,"column":null"#),
+            "{}",
+            json
+        );
+        assert!(!json.contains('\n'));
+    }
+}
@@ -0,0 +1,337 @@
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::ToTokens;
+use syn::parse::{Parse, ParseStream};
+
+/// The code an annotation is attached to.
+///
+/// Items at module level and items inside impl blocks and traits are handed to the macro
+/// directly. Fields and variants cannot carry attribute macros in Rust; annotations on them are
+/// evaluated by the `#[bestbefore]` on the enclosing struct or enum instead.
+pub enum Target {
+    Item(syn::Item),
+    ImplItem(syn::ImplItem),
+    TraitItem(syn::TraitItem),
+    Field(syn::Field),
+    Variant(syn::Variant),
+}
+
+impl Parse for Target {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // Methods, associated consts and types of impl blocks parse as items as well. Trait
+        // items without a body or default do not, and end up verbatim.
+        let fork = input.fork();
+        if let Ok(item) = fork.parse::<syn::Item>() {
+            if fork.is_empty() && !matches!(item, syn::Item::Verbatim(_)) {
+                input.parse::<syn::Item>()?;
+                return Ok(Target::Item(item));
+            }
+        }
+        let fork = input.fork();
+        if let Ok(item) = fork.parse::<syn::ImplItem>() {
+            if fork.is_empty() && !matches!(item, syn::ImplItem::Verbatim(_)) {
+                input.parse::<syn::ImplItem>()?;
+                return Ok(Target::ImplItem(item));
+            }
+        }
+        let fork = input.fork();
+        if let Ok(item) = fork.parse::<syn::TraitItem>() {
+            if fork.is_empty() && !matches!(item, syn::TraitItem::Verbatim(_)) {
+                input.parse::<syn::TraitItem>()?;
+                return Ok(Target::TraitItem(item));
+            }
+        }
+        let fork = input.fork();
+        if let Ok(variant) = fork.parse::<syn::Variant>() {
+            if fork.is_empty() {
+                input.parse::<syn::Variant>()?;
+                return Ok(Target::Variant(variant));
+            }
+        }
+        let fork = input.fork();
+        if let Ok(field) = fork.call(syn::Field::parse_named) {
+            if fork.is_empty() {
+                input.call(syn::Field::parse_named)?;
+                return Ok(Target::Field(field));
+            }
+        }
+        input.parse().map(Target::Item)
+    }
+}
+
+impl ToTokens for Target {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        match self {
+            Target::Item(item) => item.to_tokens(tokens),
+            Target::ImplItem(item) => item.to_tokens(tokens),
+            Target::TraitItem(item) => item.to_tokens(tokens),
+            Target::Field(field) => field.to_tokens(tokens),
+            Target::Variant(variant) => variant.to_tokens(tokens),
+        }
+    }
+}
+
+impl Target {
+    /// How the target is referred to in diagnostics.
+    pub fn name(&self) -> String {
+        match self {
+            Target::Item(item) => item_name(item),
+            Target::ImplItem(item) => match item {
+                syn::ImplItem::Const(item) => format!("const {}", item.ident),
+                syn::ImplItem::Fn(item) => item.sig.ident.to_string(),
+                syn::ImplItem::Type(item) => format!("type {}", item.ident),
+                _ => "code block".to_string(),
+            },
+            Target::TraitItem(item) => match item {
+                syn::TraitItem::Const(item) => format!("const {}", item.ident),
+                syn::TraitItem::Fn(item) => item.sig.ident.to_string(),
+                syn::TraitItem::Type(item) => format!("type {}", item.ident),
+                _ => "code block".to_string(),
+            },
+            Target::Field(field) => match &field.ident {
+                Some(ident) => format!("field {}", ident),
+                None => "field".to_string(),
+            },
+            Target::Variant(variant) => format!("variant {}", variant.ident),
+        }
+    }
+
+    /// Returns `true` for structs, unions and enums, whose fields and variants may carry
+    /// annotations of their own.
+    pub fn has_members(&self) -> bool {
+        matches!(
+            self,
+            Target::Item(syn::Item::Struct(_) | syn::Item::Union(_) | syn::Item::Enum(_))
+        )
+    }
+
+    /// Returns `true` if the target is an item of an impl block, which may be a trait impl.
+    ///
+    /// Methods parse as free functions, so they are recognized by their signature: only an
+    /// associated function can take `self` or refer to `Self`. Other associated functions cannot
+    /// be told apart from free ones.
+    pub fn in_impl(&self) -> bool {
+        match self {
+            Target::ImplItem(_) => true,
+            Target::Item(syn::Item::Fn(item)) => {
+                item.sig.receiver().is_some() || mentions_self(item.sig.to_token_stream())
+            }
+            _ => false,
+        }
+    }
+
+    /// The name the target's item path ends in, if it has a path the macro can tell.
+    ///
+    /// Items of impl blocks and traits have none, as their path goes through a type or trait the
+    /// macro does not see.
+    pub fn path_name(&self) -> Option<String> {
+        match self {
+            Target::ImplItem(_) | Target::TraitItem(_) => None,
+            _ if self.in_impl() => None,
+            _ => self.ident().map(|ident| ident.to_string()),
+        }
+    }
+
+    /// The attributes of the target, if it has any.
+    pub fn attrs_mut(&mut self) -> Option<&mut Vec<syn::Attribute>> {
+        match self {
+            Target::Item(item) => item_attrs_mut(item),
+            Target::ImplItem(item) => match item {
+                syn::ImplItem::Const(item) => Some(&mut item.attrs),
+                syn::ImplItem::Fn(item) => Some(&mut item.attrs),
+                syn::ImplItem::Type(item) => Some(&mut item.attrs),
+                syn::ImplItem::Macro(item) => Some(&mut item.attrs),
+                _ => None,
+            },
+            Target::TraitItem(item) => match item {
+                syn::TraitItem::Const(item) => Some(&mut item.attrs),
+                syn::TraitItem::Fn(item) => Some(&mut item.attrs),
+                syn::TraitItem::Type(item) => Some(&mut item.attrs),
+                syn::TraitItem::Macro(item) => Some(&mut item.attrs),
+                _ => None,
+            },
+            Target::Field(field) => Some(&mut field.attrs),
+            Target::Variant(variant) => Some(&mut variant.attrs),
+        }
+    }
+
+    /// The identifier of the target, if it has one.
+    pub fn ident(&self) -> Option<syn::Ident> {
+        match self {
+            Target::Item(item) => item_ident(item),
+            Target::ImplItem(item) => match item {
+                syn::ImplItem::Const(item) => Some(item.ident.clone()),
+                syn::ImplItem::Fn(item) => Some(item.sig.ident.clone()),
+                syn::ImplItem::Type(item) => Some(item.ident.clone()),
+                _ => None,
+            },
+            Target::TraitItem(item) => match item {
+                syn::TraitItem::Const(item) => Some(item.ident.clone()),
+                syn::TraitItem::Fn(item) => Some(item.sig.ident.clone()),
+                syn::TraitItem::Type(item) => Some(item.ident.clone()),
+                _ => None,
+            },
+            Target::Field(field) => field.ident.clone(),
+            Target::Variant(variant) => Some(variant.ident.clone()),
+        }
+    }
+
+    /// Places items that have to be compiled along with the target, but must not be visible
+    /// outside of it, where the target allows it.
+    ///
+    /// Module-level items are followed by the hidden items. Functions and constants, which may
+    /// just as well be associated items where no extra items are allowed, get them inside their
+    /// body or initializer. Returns `false` if the target has no place for them.
+    pub fn place_hidden(&mut self, hidden: TokenStream2, trailer: &mut TokenStream2) -> bool {
+        if hidden.is_empty() {
+            return true;
+        }
+        let (block, expr) = match self {
+            Target::Item(syn::Item::Fn(item)) => (Some(&mut *item.block), None),
+            Target::Item(syn::Item::Const(item)) => (None, Some(&mut *item.expr)),
+            Target::Item(syn::Item::Static(item)) => (None, Some(&mut *item.expr)),
+            // Type aliases may be associated types and macros may expand to anything.
+            Target::Item(syn::Item::Type(_) | syn::Item::Macro(_) | syn::Item::Verbatim(_)) => {
+                (None, None)
+            }
+            Target::Item(_) => {
+                trailer.extend(hidden);
+                return true;
+            }
+            Target::ImplItem(syn::ImplItem::Fn(item)) => (Some(&mut item.block), None),
+            Target::ImplItem(syn::ImplItem::Const(item)) => (None, Some(&mut item.expr)),
+            Target::TraitItem(syn::TraitItem::Fn(item)) => (item.default.as_mut(), None),
+            Target::TraitItem(syn::TraitItem::Const(item)) => {
+                (None, item.default.as_mut().map(|(_, expr)| expr))
+            }
+            _ => (None, None),
+        };
+        if let Some(block) = block {
+            block
+                .stmts
+                .insert(0, syn::Stmt::Item(syn::Item::Verbatim(hidden)));
+            true
+        } else if let Some(expr) = expr {
+            *expr = syn::parse_quote!({ #hidden #expr });
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn mentions_self(tokens: TokenStream2) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => ident == "Self",
+        TokenTree::Group(group) => mentions_self(group.stream()),
+        _ => false,
+    })
+}
+
+fn item_attrs_mut(item: &mut syn::Item) -> Option<&mut Vec<syn::Attribute>> {
+    match item {
+        syn::Item::Const(item) => Some(&mut item.attrs),
+        syn::Item::Enum(item) => Some(&mut item.attrs),
+        syn::Item::ExternCrate(item) => Some(&mut item.attrs),
+        syn::Item::Fn(item) => Some(&mut item.attrs),
+        syn::Item::ForeignMod(item) => Some(&mut item.attrs),
+        syn::Item::Impl(item) => Some(&mut item.attrs),
+        syn::Item::Macro(item) => Some(&mut item.attrs),
+        syn::Item::Mod(item) => Some(&mut item.attrs),
+        syn::Item::Static(item) => Some(&mut item.attrs),
+        syn::Item::Struct(item) => Some(&mut item.attrs),
+        syn::Item::Trait(item) => Some(&mut item.attrs),
+        syn::Item::TraitAlias(item) => Some(&mut item.attrs),
+        syn::Item::Type(item) => Some(&mut item.attrs),
+        syn::Item::Union(item) => Some(&mut item.attrs),
+        syn::Item::Use(item) => Some(&mut item.attrs),
+        _ => None,
+    }
+}
+
+fn item_ident(item: &syn::Item) -> Option<syn::Ident> {
+    match item {
+        syn::Item::Fn(item_fn) => Some(item_fn.sig.ident.clone()),
+        syn::Item::Mod(item_mod) => Some(item_mod.ident.clone()),
+        syn::Item::Trait(item_trait) => Some(item_trait.ident.clone()),
+        syn::Item::Struct(item_struct) => Some(item_struct.ident.clone()),
+        syn::Item::Enum(item_enum) => Some(item_enum.ident.clone()),
+        syn::Item::Union(item_union) => Some(item_union.ident.clone()),
+        syn::Item::Type(item_type) => Some(item_type.ident.clone()),
+        syn::Item::Const(item_const) => Some(item_const.ident.clone()),
+        syn::Item::Static(item_static) => Some(item_static.ident.clone()),
+        _ => None,
+    }
+}
+
+fn item_name(item: &syn::Item) -> String {
+    match item {
+        syn::Item::Fn(item_fn) => item_fn.sig.ident.to_string(),
+        syn::Item::Mod(item_mod) => item_mod.ident.to_string(),
+        syn::Item::Impl(item_impl) => impl_name(item_impl),
+        syn::Item::Trait(item_trait) => format!("trait {}", item_trait.ident),
+        syn::Item::Struct(item_struct) => format!("struct {}", item_struct.ident),
+        syn::Item::Enum(item_enum) => format!("enum {}", item_enum.ident),
+        syn::Item::Union(item_union) => format!("union {}", item_union.ident),
+        syn::Item::Const(item_const) => format!("const {}", item_const.ident),
+        syn::Item::Static(item_static) => format!("static {}", item_static.ident),
+        syn::Item::Type(item_type) => format!("type {}", item_type.ident),
+        _ => "code block".to_string(),
+    }
+}
+
+/// Names an impl block the way it is written, e.g. `impl Display for Wrapper<T>`.
+fn impl_name(item_impl: &syn::ItemImpl) -> String {
+    let self_ty = render_tokens(&item_impl.self_ty);
+    match &item_impl.trait_ {
+        Some((negation, path, _)) => format!(
+            "impl {}{} for {}",
+            if negation.is_some() { "!" } else { "" },
+            render_tokens(path),
+            self_ty
+        ),
+        None => format!("impl {}", self_ty),
+    }
+}
+
+/// Renders tokens without the spaces `TokenStream::to_string` puts around punctuation.
+pub fn render_tokens<T: ToTokens>(tokens: &T) -> String {
+    let mut rendered = tokens.to_token_stream().to_string();
+    for (spaced, tight) in [
+        (" :: ", "::"),
+        (":: ", "::"),
+        (" < ", "<"),
+        ("< ", "<"),
+        (" <", "<"),
+        (" >", ">"),
+        (" ,", ","),
+        ("& ", "&"),
+        (" (", "("),
+        ("( ", "("),
+        (" )", ")"),
+        ("[ ", "["),
+        (" ]", "]"),
+    ] {
+        rendered = rendered.replace(spaced, tight);
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path_name(target: &str) -> Option<String> {
+        syn::parse_str::<Target>(target).unwrap().path_name()
+    }
+
+    #[test]
+    fn items_of_impl_blocks_and_traits_have_no_path() {
+        assert_eq!(path_name("fn fee() -> u32 { 1 }"), Some("fee".to_string()));
+        assert_eq!(path_name("struct Fee;"), Some("Fee".to_string()));
+        assert_eq!(path_name("fn new() -> Self { Self }"), None);
+        assert_eq!(path_name("fn total(&self) -> u32 { 1 }"), None);
+        assert_eq!(path_name("fn total(&self) -> u32;"), None);
+        assert_eq!(path_name("type Output = u32;"), Some("Output".to_string()));
+        assert_eq!(path_name("type Output;"), None);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::config::Config;
+use crate::environment;
+use regex_lite::Regex;
+
+/// How ticket references are checked and linked, configured per project.
+///
+/// Both settings are taken from the project configuration, and can be overridden by environment
+/// variables:
+/// * `ticket_url` or `BESTBEFORE_TICKET_URL`: a URL template containing `{ticket}`
+/// * `ticket_pattern` or `BESTBEFORE_TICKET_PATTERN`: a regular expression every ticket has to
+///   match as a whole
+#[derive(Debug, Default)]
+pub struct TicketConfig {
+    url_template: Option<String>,
+    /// The pattern as configured, and compiled to match whole tickets.
+    pattern: Option<(String, Regex)>,
+}
+
+impl TicketConfig {
+    pub fn resolve(config: &Config) -> Result<Self, String> {
+        TicketConfig::from_vars(config, |name| environment::var(name).ok())
+    }
+
+    /// Resolves the settings as [`TicketConfig::resolve`] does, with the variables looked up by
+    /// `var`.
+    fn from_vars(config: &Config, var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let url_template = match setting(
+            var("BESTBEFORE_TICKET_URL"),
+            "BESTBEFORE_TICKET_URL",
+            "ticket_url",
+            &config.ticket_url,
+            config,
+        ) {
+            Some((template, origin)) if !template.contains("{ticket}") => {
+                return Err(format!(
+                    "Invalid {}: '{}' does not contain {{ticket}}",
+                    origin, template
+                ))
+            }
+            Some((template, _)) => Some(template),
+            None => None,
+        };
+        let pattern = match setting(
+            var("BESTBEFORE_TICKET_PATTERN"),
+            "BESTBEFORE_TICKET_PATTERN",
+            "ticket_pattern",
+            &config.ticket_pattern,
+            config,
+        ) {
+            Some((pattern, origin)) => {
+                let regex = Regex::new(&format!("^(?:{})$", pattern))
+                    .map_err(|err| format!("Invalid {}: {}", origin, err))?;
+                Some((pattern, regex))
+            }
+            None => None,
+        };
+        Ok(TicketConfig {
+            url_template,
+            pattern,
+        })
+    }
+
+    /// Checks `ticket` against the configured pattern.
+    pub fn check(&self, ticket: &str) -> Result<(), String> {
+        if ticket.trim().is_empty() {
+            return Err("ticket must not be empty".to_string());
+        }
+        match &self.pattern {
+            Some((pattern, regex)) if !regex.is_match(ticket) => Err(format!(
+                "Invalid ticket '{}': expected a ticket matching '{}'",
+                ticket, pattern
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The link to `ticket`, or the ticket itself if no URL template is configured.
+    pub fn link(&self, ticket: &str) -> String {
+        match &self.url_template {
+            Some(template) => template.replace("{ticket}", ticket),
+            None => ticket.to_string(),
+        }
+    }
+}
+
+/// A setting from the environment variable `variable`, whose value is `value`, or else from the
+/// configuration, along with where it came from.
+fn setting(
+    value: Option<String>,
+    variable: &str,
+    key: &str,
+    configured: &Option<String>,
+    config: &Config,
+) -> Option<(String, String)> {
+    match value {
+        Some(value) => Some((value, format!("{} environment variable", variable))),
+        None => configured
+            .clone()
+            .map(|value| (value, format!("{} in {}", key, config.origin()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const URL: &str = "https://tracker.local/browse/{ticket}";
+
+    fn config(ticket_url: Option<&str>, ticket_pattern: Option<&str>) -> Config {
+        Config {
+            path: Some(PathBuf::from("bestbefore.toml")),
+            ticket_url: ticket_url.map(str::to_string),
+            ticket_pattern: ticket_pattern.map(str::to_string),
+            ..Config::default()
+        }
+    }
+
+    fn resolve(config: &Config, vars: &[(&str, &str)]) -> Result<TicketConfig, String> {
+        TicketConfig::from_vars(config, |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn links_substitute_the_ticket() {
+        let tickets = resolve(&config(Some(URL), None), &[]).unwrap();
+        assert_eq!(
+            tickets.link("BILL-42"),
+            "https://tracker.local/browse/BILL-42"
+        );
+        let tickets = resolve(&config(Some("{ticket}: see {ticket}"), None), &[]).unwrap();
+        assert_eq!(tickets.link("#7"), "#7: see #7");
+    }
+
+    #[test]
+    fn links_are_the_ticket_without_a_template() {
+        let tickets = resolve(&config(None, None), &[]).unwrap();
+        assert_eq!(tickets.link("BILL-42"), "BILL-42");
+    }
+
+    #[test]
+    fn the_environment_overrides_the_configuration() {
+        let tickets = resolve(
+            &config(Some(URL), Some("[0-9]+")),
+            &[
+                ("BESTBEFORE_TICKET_URL", "https://issues.local/{ticket}"),
+                ("BESTBEFORE_TICKET_PATTERN", "[A-Z]+-[0-9]+"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(tickets.link("BILL-42"), "https://issues.local/BILL-42");
+        assert_eq!(tickets.check("BILL-42"), Ok(()));
+        assert!(tickets.check("42").is_err());
+    }
+
+    #[test]
+    fn templates_need_a_placeholder() {
+        assert_eq!(
+            resolve(&config(Some("https://tracker.local/browse/"), None), &[]).unwrap_err(),
+            "Invalid ticket_url in bestbefore.toml: \
+             'https://tracker.local/browse/' does not contain {ticket}"
+        );
+        assert_eq!(
+            resolve(
+                &config(Some(URL), None),
+                &[("BESTBEFORE_TICKET_URL", "https://issues.local/{id}")]
+            )
+            .unwrap_err(),
+            "Invalid BESTBEFORE_TICKET_URL environment variable: \
+             'https://issues.local/{id}' does not contain {ticket}"
+        );
+    }
+
+    #[test]
+    fn patterns_match_whole_tickets() {
+        let tickets = resolve(&config(None, Some("[A-Z]+-[0-9]+|#[0-9]+")), &[]).unwrap();
+        assert_eq!(tickets.check("BILL-42"), Ok(()));
+        assert_eq!(tickets.check("#7"), Ok(()));
+        assert_eq!(
+            tickets.check("see BILL-42"),
+            Err(
+                "Invalid ticket 'see BILL-42': expected a ticket matching '[A-Z]+-[0-9]+|#[0-9]+'"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            tickets.check(" "),
+            Err("ticket must not be empty".to_string())
+        );
+        let err = resolve(&config(None, Some("[A-Z")), &[]).unwrap_err();
+        assert!(
+            err.starts_with("Invalid ticket_pattern in bestbefore.toml: "),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,970 @@
+//! `cargo bestbefore bump`: moves the dates of annotations by rewriting their literals in place.
+//!
+//! Only the date literals change, so the formatting and comments around them are kept. Changes
+//! are printed as a diff unless they are written, and can be recorded as `[[snooze]]` entries of
+//! the configuration, whose limits then apply to the bump.
+
+use crate::scan::{display_path, Finding};
+use bestbefore_core::args::StageDate;
+use bestbefore_core::config::{Config, Snooze, SnoozeKey};
+use bestbefore_core::date::BestBeforeDate;
+use chrono::{Datelike, Months, NaiveDate};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Table;
+
+/// Lines of context around the changes in the diff.
+const CONTEXT: usize = 3;
+
+/// The arguments of `bump`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Args {
+    /// The new deadline, as given with `--to`.
+    pub(crate) to: Option<String>,
+    /// The `id`s of the annotations to move.
+    pub(crate) ids: Vec<String>,
+    /// The range of deadlines of the annotations to move, as given with `--due`.
+    pub(crate) due: Option<String>,
+    /// Whether to write the changes rather than print them.
+    pub(crate) write: bool,
+    /// The reason for the bump, which is recorded along with the approver.
+    pub(crate) reason: Option<String>,
+    pub(crate) approver: Option<String>,
+}
+
+/// Moves the deadlines of the selected `findings` to `args.to`, with the earlier stages of each
+/// annotation moving along by as many months.
+///
+/// `findings` are those of the workspace at `root`, already limited to the owners given.
+pub(crate) fn run(root: &Path, findings: &[Finding], args: &Args) -> Result<(), String> {
+    let to_text = args.to.as_deref().unwrap_or_default();
+    let to =
+        BestBeforeDate::parse(to_text).map_err(|err| format!("Invalid --to: {}", err.message))?;
+    let due = args.due.as_deref().map(due_range).transpose()?;
+    let selected = findings
+        .iter()
+        .filter(|finding| {
+            args.ids.is_empty()
+                || finding
+                    .args
+                    .id
+                    .as_ref()
+                    .is_some_and(|id| args.ids.contains(id))
+        })
+        .filter(|finding| {
+            due.is_none_or(|(from, until)| (from..=until).contains(&finding.deadline()))
+        })
+        .collect::<Vec<_>>();
+
+    // The new text of every literal, by file and line.
+    let mut literals: BTreeMap<&Path, BTreeMap<usize, Vec<Literal>>> = BTreeMap::new();
+    let mut sources: BTreeMap<&Path, Lines> = BTreeMap::new();
+    let mut moved = Vec::new();
+    for finding in selected {
+        if !sources.contains_key(finding.file.as_path()) {
+            let path = root.join(&finding.file);
+            let text = fs::read_to_string(&path)
+                .map_err(|err| format!("Cannot read {}: {}", path.display(), err))?;
+            sources.insert(&finding.file, Lines::new(&text));
+        }
+        let lines = &sources[finding.file.as_path()];
+        let changes = move_literals(finding, lines, &to, to_text)?;
+        if changes.is_empty() {
+            continue;
+        }
+        for literal in changes {
+            literals
+                .entry(&finding.file)
+                .or_default()
+                .entry(literal.line)
+                .or_default()
+                .push(literal);
+        }
+        moved.push(finding);
+    }
+    if moved.is_empty() {
+        eprintln!("No annotations to move");
+        return Ok(());
+    }
+
+    let mut changes = Vec::new();
+    for (file, by_line) in literals {
+        let mut lines = sources.remove(file).unwrap_or_else(|| Lines::new(""));
+        for (line, mut line_literals) in by_line {
+            // Replaced from the end, so the offsets of the others stay valid.
+            line_literals.sort_by_key(|literal| std::cmp::Reverse(literal.start));
+            let mut text = lines.old[line].clone();
+            for literal in line_literals {
+                text.replace_range(literal.start..literal.end, &literal.text);
+            }
+            lines.replace(line, text);
+        }
+        changes.push(Change {
+            path: display_path(file),
+            file: root.join(file),
+            exists: true,
+            lines,
+        });
+    }
+    if let (Some(reason), Some(approver)) = (&args.reason, &args.approver) {
+        changes.extend(record(root, &moved, to_text, reason, approver)?);
+    }
+
+    let files = changes.len();
+    let summary = format!(
+        "{} in {}",
+        count(moved.len(), "annotation"),
+        count(files, "file")
+    );
+    if args.write {
+        for change in &changes {
+            fs::write(&change.file, change.lines.text())
+                .map_err(|err| format!("Cannot write {}: {}", change.file.display(), err))?;
+        }
+        eprintln!("Moved {}", summary);
+    } else {
+        for change in &changes {
+            print!("{}", change.diff());
+        }
+        eprintln!("Would move {}; run again with --write to apply", summary);
+    }
+    Ok(())
+}
+
+/// A date literal and its replacement, located by byte offsets in its line.
+struct Literal {
+    line: usize,
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// The literals of `finding` that change when its deadline moves to `to`.
+fn move_literals(
+    finding: &Finding,
+    lines: &Lines,
+    to: &BestBeforeDate,
+    to_text: &str,
+) -> Result<Vec<Literal>, String> {
+    // An expiry added after the grace period has the span of the stage it follows and is not
+    // written anywhere.
+    let mut stages: Vec<&StageDate> = Vec::new();
+    for stage in &finding.args.stages {
+        if !stages
+            .iter()
+            .any(|written| written.date.span.start() == stage.date.span.start())
+        {
+            stages.push(stage);
+        }
+    }
+    let Some(last) = stages.last() else {
+        return Ok(Vec::new());
+    };
+    let months = (to.start().year() - last.date.value.start().year()) * 12
+        + to.start().month() as i32
+        - last.date.value.start().month() as i32;
+
+    let mut ladder = Vec::new();
+    let mut literals = Vec::new();
+    for (index, stage) in stages.iter().enumerate() {
+        let (start, end) = (stage.date.span.start(), stage.date.span.end());
+        let line = start.line - 1;
+        let Some(source) = lines.old.get(line).filter(|_| end.line == start.line) else {
+            return Err(format!(
+                "{}: cannot find the date literals of {}",
+                finding.position(),
+                finding.subject.name
+            ));
+        };
+        let (start, end) = (offset(source, start.column), offset(source, end.column));
+        let old = source[start..end]
+            .trim_start_matches('r')
+            .trim_matches('#')
+            .trim_matches('"');
+        let text = if index == stages.len() - 1 {
+            // Written like the old date, unless that would denote another period.
+            let like = render(old, to.start());
+            if BestBeforeDate::parse(&like).is_ok_and(|like| like == *to) {
+                like
+            } else {
+                to_text.to_string()
+            }
+        } else {
+            let day = shift(stage.date.value.start(), months).ok_or_else(|| {
+                format!(
+                    "{}: cannot move {} by {} months",
+                    finding.position(),
+                    old,
+                    months
+                )
+            })?;
+            render(old, day)
+        };
+        let value = BestBeforeDate::parse(&text).map_err(|err| err.message)?;
+        ladder.push((stage.stage, value));
+        if text != old {
+            literals.push(Literal {
+                line,
+                start,
+                end,
+                text: format!("\"{}\"", text),
+            });
+        }
+    }
+
+    // The ladder has to stay in order, as the macro requires.
+    for pair in ladder.windows(2) {
+        let ((earlier, earlier_date), (later, later_date)) = (pair[0], pair[1]);
+        if later_date <= earlier_date {
+            return Err(format!(
+                "{}: moving {} to {} would put its {} date ({}) at or before its {} date ({}); change these dates by hand",
+                finding.position(),
+                finding.subject.name,
+                to_text,
+                later.key(),
+                later_date,
+                earlier.key(),
+                earlier_date
+            ));
+        }
+    }
+    Ok(literals)
+}
+
+/// The byte offset of the character at `column` of `line`.
+fn offset(line: &str, column: usize) -> usize {
+    line.char_indices()
+        .nth(column)
+        .map_or(line.len(), |(offset, _)| offset)
+}
+
+/// `day` moved by `months`, which may be negative.
+fn shift(day: NaiveDate, months: i32) -> Option<NaiveDate> {
+    let by = Months::new(months.unsigned_abs());
+    if months < 0 {
+        day.checked_sub_months(by)
+    } else {
+        day.checked_add_months(by)
+    }
+}
+
+/// The period of `day` written like `like`, such as "07.2026" for a day in July 2026 and a
+/// date like "03.2025".
+fn render(like: &str, day: NaiveDate) -> String {
+    let quarter = day.month0() / 3 + 1;
+    if let Some((first, _)) = like.split_once('.') {
+        return if first.starts_with(['Q', 'q']) {
+            format!("Q{}.{:04}", quarter, day.year())
+        } else {
+            format!("{:02}.{:04}", day.month(), day.year())
+        };
+    }
+    let parts = like.split('-').collect::<Vec<_>>();
+    match parts.as_slice() {
+        [_, _, _] => day.format("%Y-%m-%d").to_string(),
+        [_, part] if part.starts_with(['W', 'w']) => {
+            let week = day.iso_week();
+            format!("{:04}-W{:02}", week.year(), week.week())
+        }
+        [_, part] if part.starts_with(['Q', 'q']) => format!("{:04}-Q{}", day.year(), quarter),
+        _ => format!("{:04}-{:02}", day.year(), day.month()),
+    }
+}
+
+/// The first and last day of `range`, such as "2025-01..2025-06", "..2025-06" or "2025-Q3".
+fn due_range(range: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let (from, until) = range.split_once("..").unwrap_or((range, range));
+    let day = |text: &str, bound: fn(&BestBeforeDate) -> NaiveDate, open: NaiveDate| {
+        if text.is_empty() {
+            return Ok(open);
+        }
+        BestBeforeDate::parse(text)
+            .map(|date| bound(&date))
+            .map_err(|err| format!("Invalid --due: {}", err.message))
+    };
+    Ok((
+        day(from, BestBeforeDate::start, NaiveDate::MIN)?,
+        day(until, BestBeforeDate::end, NaiveDate::MAX)?,
+    ))
+}
+
+/// Records the bump of `moved` in the `[[snooze]]` entries of their configuration, extending
+/// the existing entries and adding new ones.
+///
+/// Fails if an entry would go past its limits, so the configuration stays valid.
+fn record(
+    root: &Path,
+    moved: &[&Finding],
+    to: &str,
+    reason: &str,
+    approver: &str,
+) -> Result<Vec<Change>, String> {
+    // The entries to write, by configuration file.
+    let mut entries: BTreeMap<PathBuf, Vec<(SnoozeKey, Option<&Snooze>)>> = BTreeMap::new();
+    for finding in moved {
+        let key = match (
+            finding.outcome.snooze,
+            &finding.args.id,
+            &finding.subject.path,
+        ) {
+            (Some(snooze), _, _) => snooze.key.clone(),
+            (None, Some(id), _) => SnoozeKey::Id(id.clone()),
+            (None, None, Some(path)) => SnoozeKey::Item(path.clone()),
+            (None, None, None) => {
+                return Err(format!(
+                    "{}: cannot record the bump of {}, which has neither an id nor an item path",
+                    finding.position(),
+                    finding.subject.name
+                ))
+            }
+        };
+        let file = finding
+            .package
+            .context
+            .config
+            .path
+            .clone()
+            .unwrap_or_else(|| root.join(&finding.package.dir).join("bestbefore.toml"));
+        let file_entries = entries.entry(file).or_default();
+        if !file_entries.iter().any(|(other, _)| *other == key) {
+            file_entries.push((key, finding.outcome.snooze));
+        }
+    }
+
+    let mut changes = Vec::new();
+    for (file, file_entries) in entries {
+        let old = if file.is_file() {
+            fs::read_to_string(&file)
+                .map_err(|err| format!("Cannot read {}: {}", file.display(), err))?
+        } else {
+            String::new()
+        };
+        let mut lines = Lines::new(&old);
+        let header = format!("[[{}snooze]]", section(&file, &old));
+        let tables = snooze_tables(&lines.old, &header);
+        for (key, existing) in file_entries {
+            let values = [
+                ("until", quote(to)),
+                ("reason", quote(reason)),
+                ("approver", quote(approver)),
+                (
+                    "extensions",
+                    existing
+                        .map_or(1, |snooze| snooze.extensions + 1)
+                        .to_string(),
+                ),
+            ];
+            let Some(existing) = existing else {
+                let mut entry = Vec::new();
+                if lines.old.last().is_some_and(|line| !line.trim().is_empty()) {
+                    entry.push("\n".to_string());
+                }
+                entry.push(format!("{}\n", header));
+                entry.push(match &key {
+                    SnoozeKey::Id(id) => format!("id = {}\n", quote(id)),
+                    SnoozeKey::Item(item) => format!("item = {}\n", quote(item)),
+                });
+                entry.extend(
+                    values
+                        .iter()
+                        .map(|(name, value)| format!("{} = {}\n", name, value)),
+                );
+                let end = lines.old.len();
+                lines.insert(end, entry);
+                continue;
+            };
+            let Some((start, end)) = tables
+                .iter()
+                .find(|(_, _, table)| matches(table, &existing.key))
+                .map(|(start, end, _)| (*start, *end))
+            else {
+                return Err(format!(
+                    "Cannot record the bump of {} in {}: only {} tables can be updated",
+                    existing.key,
+                    file.display(),
+                    header
+                ));
+            };
+            let mut missing = Vec::new();
+            for (name, value) in values {
+                match (start..end).find(|&index| key_of(&lines.old[index]) == Some(name)) {
+                    Some(index) => {
+                        let line = with_value(&lines.old[index], &value);
+                        lines.replace(index, line);
+                    }
+                    None => missing.push(format!("{} = {}\n", name, value)),
+                }
+            }
+            // After the last line of the entry that is not blank.
+            let after = (start..end)
+                .rev()
+                .find(|&index| !lines.old[index].trim().is_empty())
+                .map_or(start, |index| index + 1);
+            lines.insert(after, missing);
+        }
+
+        // Nothing is written that the macro would reject, such as a snooze past its limits.
+        Config::parse(&file, &lines.text())
+            .map_err(|message| format!("Cannot record the bump: {}", message))?;
+        changes.push(Change {
+            path: display_path(file.strip_prefix(root).unwrap_or(&file)),
+            exists: file.is_file(),
+            file,
+            lines,
+        });
+    }
+    Ok(changes)
+}
+
+/// The prefix of the `snooze` tables in the configuration file at `path` with content `text`.
+fn section(path: &Path, text: &str) -> &'static str {
+    if path.file_name().is_none_or(|name| name != "Cargo.toml") {
+        return "";
+    }
+    let in_workspace = text.parse::<Table>().is_ok_and(|table| {
+        table
+            .get("package")
+            .and_then(|package| package.get("metadata"))
+            .and_then(|metadata| metadata.get("bestbefore"))
+            .is_none()
+            && table.contains_key("workspace")
+    });
+    if in_workspace {
+        "workspace.metadata.bestbefore."
+    } else {
+        "package.metadata.bestbefore."
+    }
+}
+
+/// The `[[snooze]]` tables with `header` in `lines`, as the range of lines after the header
+/// and their content.
+fn snooze_tables(lines: &[String], header: &str) -> Vec<(usize, usize, Table)> {
+    let mut tables = Vec::new();
+    let mut start = None;
+    for index in 0..=lines.len() {
+        let compact = lines.get(index).map(|line| {
+            let code = line.split('#').next().unwrap_or_default();
+            code.split_whitespace().collect::<String>()
+        });
+        if index < lines.len() && !compact.as_ref().is_some_and(|code| code.starts_with('[')) {
+            continue;
+        }
+        if let Some(start) = start.take() {
+            if let Ok(table) = lines[start..index].concat().parse::<Table>() {
+                tables.push((start, index, table));
+            }
+        }
+        if compact.as_deref() == Some(header) {
+            start = Some(index + 1);
+        }
+    }
+    tables
+}
+
+/// Whether the `[[snooze]]` table is the entry for `key`.
+fn matches(table: &Table, key: &SnoozeKey) -> bool {
+    let value = |name: &str| table.get(name).and_then(|value| value.as_str());
+    match key {
+        SnoozeKey::Id(id) => value("id") == Some(id),
+        SnoozeKey::Item(item) => value("item") == Some(item),
+    }
+}
+
+/// The key set on `line`, if it is a key-value pair.
+fn key_of(line: &str) -> Option<&str> {
+    let (key, _) = line.split_once('=')?;
+    Some(key.trim().trim_matches('"'))
+}
+
+/// `line` with `value` in place of its value, keeping the spacing and any trailing comment.
+fn with_value(line: &str, value: &str) -> String {
+    let Some((key, rest)) = line.split_once('=') else {
+        return line.to_string();
+    };
+    let content = rest.trim_end_matches(['\r', '\n']);
+    let ending = &rest[content.len()..];
+    let old = content.trim_start();
+    let spacing = &content[..content.len() - old.len()];
+    let old_end = match old.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let mut escaped = false;
+            old.char_indices()
+                .skip(1)
+                .find(|&(_, c)| {
+                    let closes = c == quote && !escaped;
+                    escaped = quote == '"' && c == '\\' && !escaped;
+                    closes
+                })
+                .map_or(old.len(), |(index, _)| index + 1)
+        }
+        _ => old.find(['#', ' ', '\t']).unwrap_or(old.len()),
+    };
+    format!("{}={}{}{}{}", key, spacing, value, &old[old_end..], ending)
+}
+
+/// `text` as a TOML basic string.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn count(n: usize, noun: &str) -> String {
+    format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" })
+}
+
+/// A file to be changed.
+struct Change {
+    /// How the file is referred to in the diff, relative to the workspace root.
+    path: String,
+    file: PathBuf,
+    /// Whether the file exists, or is created by the change.
+    exists: bool,
+    lines: Lines,
+}
+
+/// The lines of a file, each with its line ending, and the changes to them.
+struct Lines {
+    old: Vec<String>,
+    new: Vec<String>,
+    /// The lines added before each line, and after the last one.
+    added: Vec<Vec<String>>,
+}
+
+/// Replaces `removed` lines from `line` on with `added`.
+struct Edit {
+    line: usize,
+    removed: usize,
+    added: Vec<String>,
+}
+
+impl Lines {
+    fn new(text: &str) -> Self {
+        let old = text
+            .split_inclusive('\n')
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        Lines {
+            new: old.clone(),
+            added: vec![Vec::new(); old.len() + 1],
+            old,
+        }
+    }
+
+    fn replace(&mut self, line: usize, text: String) {
+        self.new[line] = text;
+    }
+
+    fn insert(&mut self, before: usize, lines: Vec<String>) {
+        if lines.is_empty() {
+            return;
+        }
+        // Lines can only be added after a line that ends.
+        if before == self.old.len() {
+            if let Some(last) = self.new.last_mut().filter(|last| !last.ends_with('\n')) {
+                last.push('\n');
+            }
+        }
+        self.added[before].extend(lines);
+    }
+
+    /// The changes as edits of consecutive lines, in order.
+    fn edits(&self) -> Vec<Edit> {
+        let mut edits: Vec<Edit> = Vec::new();
+        for line in 0..=self.old.len() {
+            let changed = line < self.old.len() && self.new[line] != self.old[line];
+            let mut added = self.added[line].clone();
+            if changed {
+                added.push(self.new[line].clone());
+            }
+            if added.is_empty() {
+                continue;
+            }
+            match edits.last_mut() {
+                Some(last) if last.line + last.removed == line => {
+                    last.removed += usize::from(changed);
+                    last.added.extend(added);
+                }
+                _ => edits.push(Edit {
+                    line,
+                    removed: usize::from(changed),
+                    added,
+                }),
+            }
+        }
+        edits
+    }
+
+    /// The changed content of the file.
+    fn text(&self) -> String {
+        let mut text = String::new();
+        for (line, added) in self.added.iter().enumerate() {
+            for added in added {
+                text.push_str(added);
+            }
+            if let Some(new) = self.new.get(line) {
+                text.push_str(new);
+            }
+        }
+        text
+    }
+}
+
+impl Change {
+    /// The change as a unified diff.
+    fn diff(&self) -> String {
+        let edits = self.lines.edits();
+        let old = &self.lines.old;
+        let mut diff = if self.exists {
+            format!("--- a/{}\n", self.path)
+        } else {
+            "--- /dev/null\n".to_string()
+        };
+        diff.push_str(&format!("+++ b/{}\n", self.path));
+
+        // Edits whose context overlaps share a hunk.
+        let mut hunks: Vec<Vec<&Edit>> = Vec::new();
+        for edit in &edits {
+            match hunks.last_mut() {
+                Some(hunk)
+                    if hunk.last().is_some_and(|last| {
+                        last.line + last.removed + 2 * CONTEXT >= edit.line
+                    }) =>
+                {
+                    hunk.push(edit)
+                }
+                _ => hunks.push(vec![edit]),
+            }
+        }
+
+        // How many more lines the new file has before the current hunk.
+        let mut shift = 0isize;
+        for hunk in hunks {
+            let start = hunk[0].line.saturating_sub(CONTEXT);
+            let last = hunk[hunk.len() - 1];
+            let end = (last.line + last.removed + CONTEXT).min(old.len());
+            let mut body = String::new();
+            let (mut old_count, mut new_count) = (0, 0);
+            let mut next = start;
+            for edit in hunk {
+                for line in &old[next..edit.line] {
+                    push_line(&mut body, ' ', line);
+                }
+                for line in &old[edit.line..edit.line + edit.removed] {
+                    push_line(&mut body, '-', line);
+                }
+                for line in &edit.added {
+                    push_line(&mut body, '+', line);
+                }
+                old_count += edit.line - next + edit.removed;
+                new_count += edit.line - next + edit.added.len();
+                next = edit.line + edit.removed;
+            }
+            for line in &old[next..end] {
+                push_line(&mut body, ' ', line);
+            }
+            old_count += end - next;
+            new_count += end - next;
+
+            let new_start = start.saturating_add_signed(shift);
+            diff.push_str(&format!(
+                "@@ -{} +{} @@\n{}",
+                range(start, old_count),
+                range(new_start, new_count),
+                body
+            ));
+            shift += new_count as isize - old_count as isize;
+        }
+        diff
+    }
+}
+
+/// Adds `line` to a diff, marked with `prefix`.
+fn push_line(diff: &mut String, prefix: char, line: &str) {
+    diff.push(prefix);
+    diff.push_str(line.trim_end_matches(['\r', '\n']));
+    diff.push('\n');
+    if !line.ends_with('\n') {
+        diff.push_str("\\ No newline at end of file\n");
+    }
+}
+
+/// The range of `count` lines from the 0-based `start` in a hunk header.
+fn range(start: usize, count: usize) -> String {
+    match count {
+        // An empty range is given by the line before it.
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn lines(text: &str) -> Lines {
+        Lines::new(text)
+    }
+
+    fn numbered(count: usize) -> String {
+        (1..=count).map(|line| format!("line {}\n", line)).collect()
+    }
+
+    fn change(lines: Lines, exists: bool) -> Change {
+        Change {
+            path: "bestbefore.toml".to_string(),
+            file: PathBuf::from("bestbefore.toml"),
+            exists,
+            lines,
+        }
+    }
+
+    #[test]
+    fn renders_like_the_old_date() {
+        let to = day("2026-07-01");
+        assert_eq!(render("03.2025", to), "07.2026");
+        assert_eq!(render("Q1.2025", to), "Q3.2026");
+        assert_eq!(render("2025-03", to), "2026-07");
+        assert_eq!(render("2025-03-15", to), "2026-07-01");
+        assert_eq!(render("2025-W10", to), "2026-W27");
+        assert_eq!(render("2025-q2", to), "2026-Q3");
+    }
+
+    #[test]
+    fn shifts_by_months() {
+        assert_eq!(shift(day("2025-01-31"), 1), Some(day("2025-02-28")));
+        assert_eq!(shift(day("2025-03-01"), -3), Some(day("2024-12-01")));
+        assert_eq!(shift(NaiveDate::MAX, 1), None);
+    }
+
+    #[test]
+    fn parses_due_ranges() {
+        assert_eq!(
+            due_range("2025-01..2025-06"),
+            Ok((day("2025-01-01"), day("2025-06-30")))
+        );
+        assert_eq!(
+            due_range("..2025-Q1"),
+            Ok((NaiveDate::MIN, day("2025-03-31")))
+        );
+        assert_eq!(
+            due_range("2025-Q3.."),
+            Ok((day("2025-07-01"), NaiveDate::MAX))
+        );
+        assert_eq!(
+            due_range("2025-Q3"),
+            Ok((day("2025-07-01"), day("2025-09-30")))
+        );
+        assert!(due_range("soon").unwrap_err().starts_with("Invalid --due"));
+    }
+
+    #[test]
+    fn replaces_values_keeping_the_rest_of_the_line() {
+        assert_eq!(
+            with_value("until = \"2025-09\"  # ledger\n", "\"2026-01\""),
+            "until = \"2026-01\"  # ledger\n"
+        );
+        assert_eq!(with_value("extensions=1\n", "2"), "extensions=2\n");
+        assert_eq!(
+            with_value("reason = \"a \\\"#\\\" b\" # why\n", "\"c\""),
+            "reason = \"c\" # why\n"
+        );
+        assert_eq!(
+            with_value("reason\t= 'x#y'\r\n", "\"z\""),
+            "reason\t= \"z\"\r\n"
+        );
+        assert_eq!(
+            with_value("until = 2025-09-01", "\"2026-01\""),
+            "until = \"2026-01\""
+        );
+        assert_eq!(with_value("[[snooze]]\n", "1"), "[[snooze]]\n");
+    }
+
+    #[test]
+    fn finds_keys() {
+        assert_eq!(key_of("until = \"2025-09\""), Some("until"));
+        assert_eq!(key_of("  \"approver\"=\"alice\""), Some("approver"));
+        assert_eq!(key_of("[[snooze]]"), None);
+    }
+
+    #[test]
+    fn quotes_basic_strings() {
+        assert_eq!(quote("plain"), "\"plain\"");
+        assert_eq!(quote("a \"b\"\\c\n"), "\"a \\\"b\\\"\\\\c\\n\"");
+        assert_eq!(quote("\u{7}"), "\"\\u0007\"");
+    }
+
+    #[test]
+    fn finds_snooze_tables() {
+        let text = "[package]\n\
+                    name = \"billing\"\n\
+                    \n\
+                    [[package.metadata.bestbefore.snooze]] # first\n\
+                    id = \"legacy\"\n\
+                    until = \"2025-09\"\n\
+                    \n\
+                    [[ package.metadata.bestbefore.snooze ]]\n\
+                    item = \"billing::fee\"\n\
+                    [package.metadata.other]\n\
+                    id = \"other\"\n\
+                    [[package.metadata.bestbefore.snooze]]\n\
+                    id = \"last\"\n";
+        let lines = lines(text);
+        let tables = snooze_tables(&lines.old, "[[package.metadata.bestbefore.snooze]]");
+        let ranges = tables
+            .iter()
+            .map(|(start, end, _)| (*start, *end))
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, [(4, 7), (8, 9), (12, 13)]);
+        assert!(matches(&tables[0].2, &SnoozeKey::Id("legacy".into())));
+        assert!(!matches(&tables[0].2, &SnoozeKey::Id("other".into())));
+        assert!(matches(
+            &tables[1].2,
+            &SnoozeKey::Item("billing::fee".into())
+        ));
+        assert!(matches(&tables[2].2, &SnoozeKey::Id("last".into())));
+        assert!(snooze_tables(&lines.old, "[[snooze]]").is_empty());
+    }
+
+    #[test]
+    fn places_snoozes_by_configuration_file() {
+        let toml = Path::new("bestbefore.toml");
+        let manifest = Path::new("Cargo.toml");
+        assert_eq!(section(toml, "[workspace]\n"), "");
+        assert_eq!(
+            section(manifest, "[package]\nname = \"billing\"\n"),
+            "package.metadata.bestbefore."
+        );
+        assert_eq!(
+            section(manifest, "[workspace]\nmembers = []\n"),
+            "workspace.metadata.bestbefore."
+        );
+        assert_eq!(
+            section(
+                manifest,
+                "[package]\n[package.metadata.bestbefore]\n[workspace]\n"
+            ),
+            "package.metadata.bestbefore."
+        );
+    }
+
+    #[test]
+    fn applies_changes() {
+        let mut lines = lines("a\nb\nc");
+        lines.replace(1, "B\n".to_string());
+        lines.insert(0, vec!["first\n".to_string()]);
+        lines.insert(3, vec!["d\n".to_string()]);
+        assert_eq!(lines.text(), "first\na\nB\nc\nd\n");
+        lines.insert(1, Vec::new());
+        assert_eq!(lines.text(), "first\na\nB\nc\nd\n");
+    }
+
+    #[test]
+    fn diffs_a_replaced_line() {
+        let mut lines = lines(&numbered(10));
+        lines.replace(1, "line two\n".to_string());
+        assert_eq!(
+            change(lines, true).diff(),
+            "--- a/bestbefore.toml\n\
+             +++ b/bestbefore.toml\n\
+             @@ -1,5 +1,5 @@\n \
+             line 1\n\
+             -line 2\n\
+             +line two\n \
+             line 3\n \
+             line 4\n \
+             line 5\n"
+        );
+    }
+
+    #[test]
+    fn diffs_lines_added_at_the_end() {
+        let mut lines = lines(&numbered(10));
+        lines.insert(10, vec!["line 11\n".to_string()]);
+        assert_eq!(
+            change(lines, true).diff(),
+            "--- a/bestbefore.toml\n\
+             +++ b/bestbefore.toml\n\
+             @@ -8,3 +8,4 @@\n \
+             line 8\n \
+             line 9\n \
+             line 10\n\
+             +line 11\n"
+        );
+    }
+
+    #[test]
+    fn diffs_a_new_file() {
+        let mut lines = lines("");
+        lines.insert(
+            0,
+            vec!["[[snooze]]\n".to_string(), "id = \"a\"\n".to_string()],
+        );
+        assert_eq!(
+            change(lines, false).diff(),
+            "--- /dev/null\n\
+             +++ b/bestbefore.toml\n\
+             @@ -0,0 +1,2 @@\n\
+             +[[snooze]]\n\
+             +id = \"a\"\n"
+        );
+    }
+
+    #[test]
+    fn diffs_a_missing_newline() {
+        let mut lines = lines("a\nb");
+        lines.insert(2, vec!["c\n".to_string()]);
+        assert_eq!(lines.text(), "a\nb\nc\n");
+        assert_eq!(
+            change(lines, true).diff(),
+            "--- a/bestbefore.toml\n\
+             +++ b/bestbefore.toml\n\
+             @@ -1,2 +1,3 @@\n \
+             a\n\
+             -b\n\
+             \\ No newline at end of file\n\
+             +b\n\
+             +c\n"
+        );
+    }
+
+    #[test]
+    fn shares_hunks_between_close_changes() {
+        let mut lines = lines(&numbered(20));
+        lines.replace(2, "line three\n".to_string());
+        lines.replace(8, "line nine\n".to_string());
+        let diff = change(lines, true).diff();
+        assert_eq!(diff.matches("@@ -").count(), 1);
+        assert!(diff.contains("@@ -1,12 +1,12 @@\n"));
+    }
+
+    #[test]
+    fn shifts_later_hunks_by_added_lines() {
+        let mut lines = lines(&numbered(20));
+        lines.insert(1, vec!["line 1.5\n".to_string()]);
+        lines.replace(15, "line sixteen\n".to_string());
+        let diff = change(lines, true).diff();
+        let headers = diff
+            .lines()
+            .filter(|line| line.starts_with("@@"))
+            .collect::<Vec<_>>();
+        assert_eq!(headers, ["@@ -1,4 +1,5 @@", "@@ -13,7 +14,7 @@"]);
+    }
+}
@@ -0,0 +1,219 @@
+//! iCalendar output, for subscribing to the dates on which code warns and expires.
+//!
+//! Every stage with diagnostics is an all-day event on the first day the stage applies. Events
+//! are identified by the fingerprint of their annotation and the stage, so calendars update
+//! them in place when dates move.
+
+use crate::scan::Finding;
+use bestbefore_core::args::Stage;
+use bestbefore_core::eval::evaluate;
+use chrono::NaiveDate;
+
+/// The stages that get an event, with how the event is titled.
+const EVENTS: &[(Stage, &str)] = &[
+    (Stage::Warn, "Warns"),
+    (Stage::DenyInCi, "Fails CI"),
+    (Stage::Error, "Expires"),
+];
+
+/// Details that change with every run rather than with the annotation.
+const VOLATILE_DETAILS: &[&str] = &["mode", "clock"];
+
+/// The calendar of `findings`, named after `owners` if the feed is limited to them.
+pub(crate) fn render(findings: &[Finding], owners: &[String], today: NaiveDate) -> String {
+    let mut events = findings
+        .iter()
+        .flat_map(|finding| events(finding, today))
+        .collect::<Vec<_>>();
+    events.sort();
+
+    let name = if owners.is_empty() {
+        "bestbefore".to_string()
+    } else {
+        format!("bestbefore: {}", owners.join(", "))
+    };
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!(
+            "PRODID:-//suprematic//cargo-bestbefore {}//EN",
+            env!("CARGO_PKG_VERSION")
+        ),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape(&name)),
+    ];
+    for (_, _, event) in events {
+        lines.extend(event);
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// The events of `finding` as (date, UID, lines), one per stage with diagnostics.
+fn events(finding: &Finding, today: NaiveDate) -> Vec<(NaiveDate, String, Vec<String>)> {
+    let stages = &finding.args.stages;
+    let item = finding
+        .subject
+        .path
+        .clone()
+        .unwrap_or_else(|| finding.subject.name.clone());
+
+    let mut events = Vec::new();
+    for (index, stage) in stages.iter().enumerate() {
+        let Some((_, title)) = EVENTS.iter().find(|(event, _)| *event == stage.stage) else {
+            continue;
+        };
+        let end = stage.date.value.end();
+        // A stage that is overtaken on its first day by a later one never shows.
+        if stages[index + 1..]
+            .iter()
+            .any(|later| later.date.value.end() == end)
+        {
+            continue;
+        }
+        let Some(start) = end.succ_opt() else {
+            continue;
+        };
+        // The message the compiler reports from that day on, outside of CI.
+        let message = evaluate(
+            &finding.args,
+            &finding.subject.name,
+            start,
+            false,
+            &finding.package.context.config,
+        )
+        .message()
+        .map(str::to_string)
+        .unwrap_or_default();
+
+        let mut description = vec![item.clone(), message];
+        description.extend(
+            finding
+                .outcome
+                .details
+                .iter()
+                .filter(|(label, _)| !VOLATILE_DETAILS.contains(label))
+                .map(|(label, value)| format!("{}: {}", label, value)),
+        );
+        description.push(format!("location: {}", finding.position()));
+
+        let uid = format!("{}-{}@bestbefore", finding.fingerprint(), stage.stage.key());
+        let mut lines = vec![
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", uid),
+            format!("DTSTAMP:{}T000000Z", today.format("%Y%m%d")),
+            format!("DTSTART;VALUE=DATE:{}", start.format("%Y%m%d")),
+            format!(
+                "DTEND;VALUE=DATE:{}",
+                start.succ_opt().unwrap_or(start).format("%Y%m%d")
+            ),
+            format!("SUMMARY:{}", escape(&format!("{}: {}", title, item))),
+            format!("DESCRIPTION:{}", escape(&description.join("\n"))),
+            format!("CATEGORIES:bestbefore,{}", stage.stage.key()),
+            "TRANSP:TRANSPARENT".to_string(),
+        ];
+        if let Some(owner) = &finding.args.owner {
+            lines.push(format!(
+                "ORGANIZER;CN=\"{}\":{}",
+                owner.replace('"', ""),
+                owner_uri(owner)
+            ));
+        }
+        lines.push("END:VEVENT".to_string());
+        events.push((start, uid, lines));
+    }
+    events
+}
+
+/// The calendar address of `owner`: their mail address if it is one, or else a URN naming them.
+fn owner_uri(owner: &str) -> String {
+    if owner.contains('@') && !owner.contains(char::is_whitespace) {
+        return format!("mailto:{}", owner);
+    }
+    let mut uri = "urn:bestbefore:owner:".to_string();
+    for byte in owner.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Escapes a text value, which may contain tabs but no other control characters.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push('\t'),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Folds `line` into lines of at most 75 octets, each ending in CRLF, without splitting
+/// characters.
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 2);
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(escape("a,b;c\\d\ne\tf\u{7}g"), "a\\,b\\;c\\\\d\\ne\tfg");
+    }
+
+    #[test]
+    fn folds_at_75_octets() {
+        assert_eq!(fold(&"a".repeat(75)), format!("{}\r\n", "a".repeat(75)));
+        assert_eq!(
+            fold(&"a".repeat(151)),
+            format!(
+                "{}\r\n {}\r\n {}\r\n",
+                "a".repeat(75),
+                "a".repeat(74),
+                "a".repeat(2)
+            )
+        );
+    }
+
+    #[test]
+    fn folds_between_characters() {
+        // "€" takes 3 octets: 24 of them fill 72, and the 25th would end at octet 75.
+        let line = "€".repeat(26);
+        assert_eq!(fold(&line), format!("{}\r\n {}\r\n", "€".repeat(25), "€"));
+        // With one more octet in front, the 25th no longer fits.
+        let line = format!("a{}", "€".repeat(26));
+        assert_eq!(
+            fold(&line),
+            format!("a{}\r\n {}\r\n", "€".repeat(24), "€".repeat(2))
+        );
+        for line in fold(&format!("ab{}", "ü€😀".repeat(40))).split_terminator("\r\n") {
+            assert!(line.len() <= 75, "{:?} is {} octets", line, line.len());
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! `cargo bestbefore check`: the warnings and errors the compiler would report, as a CI gate.
+
+use crate::scan::Finding;
+use crate::{ci, junit, list, sarif, Format};
+use bestbefore_core::eval::Verdict;
+
+/// Exit code when there are warnings, but nothing has expired.
+pub(crate) const EXIT_WARNING: u8 = 1;
+/// Exit code when code has expired.
+pub(crate) const EXIT_EXPIRED: u8 = 2;
+
+/// Prints the diagnostics of `findings` in `format` and returns the exit code.
+pub(crate) fn run(findings: &[Finding], format: Format) -> u8 {
+    match format {
+        Format::Json => print!("{}", list::json(findings)),
+        Format::Sarif => print!("{}", sarif::render(findings)),
+        Format::Junit => print!("{}", junit::render(findings)),
+        Format::Github => print!("{}", ci::github(findings)),
+        Format::Gitlab => print!("{}", ci::gitlab(findings)),
+        _ => print!("{}", text(findings)),
+    }
+    exit_code(findings)
+}
+
+/// [`EXIT_EXPIRED`] if any annotation fails the build, [`EXIT_WARNING`] if any warns, else 0.
+pub(crate) fn exit_code(findings: &[Finding]) -> u8 {
+    let due = |state| {
+        findings
+            .iter()
+            .any(|finding| finding.outcome.verdict.state() == state)
+    };
+    if due("error") {
+        EXIT_EXPIRED
+    } else if due("warning") {
+        EXIT_WARNING
+    } else {
+        0
+    }
+}
+
+/// The diagnostics in the style of the compiler, followed by a summary.
+fn text(findings: &[Finding]) -> String {
+    let mut text = String::new();
+    let mut counts = [0; 4];
+    for finding in findings {
+        let (index, level) = match &finding.outcome.verdict {
+            Verdict::Fail { .. } => (0, Some("error")),
+            Verdict::Warn { .. } => (1, Some("warning")),
+            Verdict::Note { .. } => (2, None),
+            Verdict::Fresh => (3, None),
+        };
+        counts[index] += 1;
+        let (Some(level), Some(message)) = (level, finding.outcome.verdict.message()) else {
+            continue;
+        };
+        text.push_str(&format!("{}: {}\n", level, message));
+        text.push_str(&format!("  --> {}\n", finding.position()));
+        for (label, value) in &finding.outcome.details {
+            text.push_str(&format!("   = {}: {}\n", label, value));
+        }
+        text.push('\n');
+    }
+
+    let summary = [
+        (counts[0], "expired", "expired"),
+        (counts[1], "warning", "warnings"),
+        (counts[2], "note", "notes"),
+        (counts[3], "fresh", "fresh"),
+    ]
+    .iter()
+    .filter(|(count, _, _)| *count > 0)
+    .map(|(count, one, many)| format!("{} {}", count, if *count == 1 { one } else { many }))
+    .collect::<Vec<_>>();
+    let annotations = if findings.len() == 1 {
+        "annotation"
+    } else {
+        "annotations"
+    };
+    if summary.is_empty() {
+        text.push_str(&format!("Checked {} {}\n", findings.len(), annotations));
+    } else {
+        text.push_str(&format!(
+            "Checked {} {}: {}\n",
+            findings.len(),
+            annotations,
+            summary.join(", ")
+        ));
+    }
+    text
+}
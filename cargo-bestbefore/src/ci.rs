@@ -0,0 +1,101 @@
+//! Annotations for CI systems that show diagnostics inline on pull and merge requests.
+
+use crate::json::Json;
+use crate::sarif::rule_id;
+use crate::scan::{display_path, Finding};
+use bestbefore_core::args::Stage;
+use bestbefore_core::eval::Verdict;
+
+/// GitHub Actions workflow commands, one `::warning` or `::error` line per diagnostic.
+pub(crate) fn github(findings: &[Finding]) -> String {
+    let mut commands = String::new();
+    for finding in findings {
+        let (command, message) = match &finding.outcome.verdict {
+            Verdict::Warn { message, .. } => ("warning", message),
+            Verdict::Fail { message, .. } => ("error", message),
+            Verdict::Fresh | Verdict::Note { .. } => continue,
+        };
+        let Some(location) = &finding.subject.location else {
+            continue;
+        };
+        let message = finding
+            .outcome
+            .details
+            .iter()
+            .fold(message.clone(), |message, (label, value)| {
+                format!("{}\n{}: {}", message, label, value)
+            });
+        let properties = [
+            ("file", display_path(&finding.file)),
+            ("line", location.line.to_string()),
+            ("col", location.column.to_string()),
+            ("endLine", finding.end.0.to_string()),
+            ("endColumn", finding.end.1.to_string()),
+            ("title", rule_id(finding.stage().unwrap_or(Stage::Warn))),
+        ]
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, escape_property(value)))
+        .collect::<Vec<_>>();
+        commands.push_str(&format!(
+            "::{} {}::{}\n",
+            command,
+            properties.join(","),
+            escape_data(&message)
+        ));
+    }
+    commands
+}
+
+/// A GitLab Code Quality report, with one issue per diagnostic.
+///
+/// Issues are fingerprinted by the identity of their annotation, so GitLab can tell which are
+/// new, which persist and which are resolved between the source and target branch.
+pub(crate) fn gitlab(findings: &[Finding]) -> String {
+    let issues = findings
+        .iter()
+        .filter_map(|finding| {
+            let (severity, message) = match &finding.outcome.verdict {
+                Verdict::Warn { message, .. } => ("minor", message),
+                Verdict::Fail { message, .. } => ("critical", message),
+                Verdict::Fresh | Verdict::Note { .. } => return None,
+            };
+            let location = finding.subject.location.as_ref()?;
+            Some(Json::object([
+                ("description", message.as_str().into()),
+                (
+                    "check_name",
+                    rule_id(finding.stage().unwrap_or(Stage::Warn)).into(),
+                ),
+                ("fingerprint", finding.fingerprint().into()),
+                ("severity", severity.into()),
+                (
+                    "location",
+                    Json::object([
+                        ("path", display_path(&finding.file).into()),
+                        (
+                            "lines",
+                            Json::object([
+                                ("begin", location.line.into()),
+                                ("end", finding.end.0.into()),
+                            ]),
+                        ),
+                    ]),
+                ),
+            ]))
+        })
+        .collect();
+    Json::Array(issues).pretty()
+}
+
+/// Escapes the message of a workflow command.
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property of a workflow command, where `:` and `,` are separators.
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
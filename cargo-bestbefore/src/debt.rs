@@ -0,0 +1,573 @@
+//! `cargo bestbefore debt`: a report of what is due when, for people rather than tools.
+//!
+//! The report is built as a list of blocks and then rendered as Markdown or as a self-contained
+//! HTML page. Both include a burndown chart as inline SVG.
+
+use crate::scan::Finding;
+use crate::xml::escape;
+use bestbefore_core::args::Stage;
+use bestbefore_core::eval::Verdict;
+use chrono::{Datelike, NaiveDate};
+use std::collections::BTreeMap;
+
+/// How many months before and after today the burndown chart shows, as deadlines may be decades
+/// apart. The table below it lists every change.
+const CHART_MONTHS: (usize, usize) = (12, 24);
+
+/// An annotation as seen by the report.
+struct Entry<'a> {
+    finding: &'a Finding<'a>,
+    /// The last day before the code warns, if it ever does.
+    warns: Option<NaiveDate>,
+    /// The last day before the code fails the build, if it ever does.
+    expires: Option<NaiveDate>,
+    owner: &'a str,
+    module: String,
+}
+
+impl Entry<'_> {
+    fn new<'a>(finding: &'a Finding<'a>) -> Entry<'a> {
+        let date = |stages: &[Stage]| {
+            finding
+                .args
+                .stages
+                .iter()
+                .find(|stage| stages.contains(&stage.stage))
+                .map(|stage| stage.date.value.end())
+        };
+        let module = finding
+            .subject
+            .krate
+            .iter()
+            .cloned()
+            .chain(
+                finding
+                    .subject
+                    .location
+                    .iter()
+                    .flat_map(|location| location.module_path()),
+            )
+            .collect::<Vec<_>>()
+            .join("::");
+        Entry {
+            finding,
+            warns: date(&[Stage::Warn, Stage::DenyInCi, Stage::Error]),
+            expires: date(&[Stage::Error]),
+            owner: finding.args.owner.as_deref().unwrap_or("unassigned"),
+            module,
+        }
+    }
+
+    /// How overdue the code is: `"expired"`, `"warning"` or neither.
+    fn overdue(&self) -> Option<&'static str> {
+        match self.finding.outcome.verdict {
+            Verdict::Fail { .. } => Some("expired"),
+            Verdict::Warn { .. } => Some("warning"),
+            Verdict::Fresh | Verdict::Note { .. } => None,
+        }
+    }
+
+    /// How the annotated code is referred to: its path, or else its name and location.
+    fn item(&self) -> String {
+        match &self.finding.subject.path {
+            Some(path) => path.clone(),
+            None => format!(
+                "{} at {}",
+                self.finding.subject.name,
+                self.finding.position()
+            ),
+        }
+    }
+}
+
+/// A table row, highlighted as `"expired"` or `"warning"` if the code is overdue.
+struct Row {
+    cells: Vec<String>,
+    highlight: Option<&'static str>,
+}
+
+/// A warning or expiry date of an annotation.
+type Event<'a> = (NaiveDate, &'static str, &'a Entry<'a>);
+
+enum Block {
+    Heading(usize, String),
+    Paragraph(String),
+    Table(Vec<&'static str>, Vec<Row>),
+    Svg(String),
+}
+
+/// The report in Markdown.
+pub(crate) fn markdown(findings: &[Finding], today: NaiveDate) -> String {
+    let mut markdown = String::new();
+    for block in blocks(findings, today) {
+        match block {
+            Block::Heading(level, text) => {
+                markdown.push_str(&format!("{} {}\n\n", "#".repeat(level), text))
+            }
+            Block::Paragraph(text) => markdown.push_str(&format!("{}\n\n", text)),
+            Block::Table(headers, rows) => {
+                markdown.push_str(&format!("| {} |\n", headers.join(" | ")));
+                markdown.push_str(&format!("|{}\n", "---|".repeat(headers.len())));
+                for row in rows {
+                    let cells = row
+                        .cells
+                        .iter()
+                        .enumerate()
+                        .map(|(index, text)| match row.highlight {
+                            Some(_) if index == 0 => format!("**{}**", markdown_cell(text)),
+                            _ => markdown_cell(text),
+                        })
+                        .collect::<Vec<_>>();
+                    markdown.push_str(&format!("| {} |\n", cells.join(" | ")));
+                }
+                markdown.push('\n');
+            }
+            Block::Svg(svg) => markdown.push_str(&format!("{}\n\n", svg)),
+        }
+    }
+    markdown
+}
+
+/// `text` as the content of a Markdown table cell, which is rendered as text rather than markup.
+fn markdown_cell(text: &str) -> String {
+    let mut cell = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => cell.push_str("&amp;"),
+            '<' => cell.push_str("&lt;"),
+            '>' => cell.push_str("&gt;"),
+            '|' => cell.push_str("\\|"),
+            '\n' | '\r' | '\t' => cell.push(' '),
+            c => cell.push(c),
+        }
+    }
+    cell
+}
+
+/// The report as an HTML page without external assets.
+pub(crate) fn html(findings: &[Finding], today: NaiveDate) -> String {
+    let mut body = String::new();
+    for block in blocks(findings, today) {
+        match block {
+            Block::Heading(level, text) => {
+                body.push_str(&format!("<h{0}>{1}</h{0}>\n", level, escape(&text)))
+            }
+            Block::Paragraph(text) => body.push_str(&format!("<p>{}</p>\n", escape(&text))),
+            Block::Table(headers, rows) => {
+                body.push_str("<table>\n<thead><tr>");
+                for header in headers {
+                    body.push_str(&format!("<th>{}</th>", escape(header)));
+                }
+                body.push_str("</tr></thead>\n<tbody>\n");
+                for row in rows {
+                    match row.highlight {
+                        Some(class) => body.push_str(&format!("<tr class=\"{}\">", class)),
+                        None => body.push_str("<tr>"),
+                    }
+                    for cell in &row.cells {
+                        body.push_str(&format!("<td>{}</td>", escape(cell)));
+                    }
+                    body.push_str("</tr>\n");
+                }
+                body.push_str("</tbody>\n</table>\n");
+            }
+            Block::Svg(svg) => body.push_str(&format!("<figure>{}</figure>\n", svg)),
+        }
+    }
+    format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<title>Best-before report</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em auto; max-width: 72em; color: #222; }}
+table {{ border-collapse: collapse; margin-bottom: 1.5em; }}
+th, td {{ border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }}
+th {{ background: #f0f0f0; }}
+tr.expired td {{ background: #f8d7da; }}
+tr.warning td {{ background: #fff3cd; }}
+figure {{ margin: 0 0 1.5em; }}
+</style>
+</head>
+<body>
+{}</body>
+</html>
+",
+        body
+    )
+}
+
+fn blocks(findings: &[Finding], today: NaiveDate) -> Vec<Block> {
+    let entries: Vec<Entry> = findings.iter().map(Entry::new).collect();
+    let expired = entries
+        .iter()
+        .filter(|entry| entry.overdue() == Some("expired"))
+        .count();
+    let warning = entries
+        .iter()
+        .filter(|entry| entry.overdue() == Some("warning"))
+        .count();
+
+    let mut blocks = vec![
+        Block::Heading(1, "Best-before report".to_string()),
+        Block::Paragraph(format!(
+            "Evaluated on {}: {} {}, {} expired, {} past the warning date.",
+            today.format("%Y-%m-%d"),
+            entries.len(),
+            if entries.len() == 1 {
+                "annotation"
+            } else {
+                "annotations"
+            },
+            expired,
+            warning
+        )),
+    ];
+    if entries.is_empty() {
+        return blocks;
+    }
+
+    blocks.push(Block::Heading(2, "Burndown".to_string()));
+    blocks.push(Block::Paragraph(format!(
+        "Annotations still in date and not yet expired at the end of each month, charted \
+             up to {} months before and {} months after today.",
+        CHART_MONTHS.0, CHART_MONTHS.1
+    )));
+    let months = burndown(&entries, today);
+    blocks.push(Block::Svg(svg(
+        chart_window(&months, today),
+        entries.len(),
+        today,
+    )));
+    // Only the months in which something changes, as the chart may span decades.
+    let mut rows = Vec::new();
+    let mut previous = (entries.len(), entries.len());
+    for (month, in_date, not_expired) in &months {
+        if (*in_date, *not_expired) != previous {
+            rows.push(Row {
+                cells: vec![
+                    month.format("%Y-%m").to_string(),
+                    in_date.to_string(),
+                    not_expired.to_string(),
+                ],
+                highlight: None,
+            });
+            previous = (*in_date, *not_expired);
+        }
+    }
+    blocks.push(Block::Table(vec!["Month", "In date", "Not expired"], rows));
+
+    let mut overdue: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| entry.overdue().is_some())
+        .collect();
+    if !overdue.is_empty() {
+        overdue.sort_by_key(|entry| (entry.overdue() != Some("expired"), entry.finding.deadline()));
+        blocks.push(Block::Heading(2, "Overdue".to_string()));
+        let rows = overdue
+            .iter()
+            .map(|entry| Row {
+                cells: vec![
+                    entry.overdue().unwrap_or_default().to_string(),
+                    entry.item(),
+                    entry.owner.to_string(),
+                    entry.finding.deadline().format("%Y-%m-%d").to_string(),
+                    entry.finding.position(),
+                ],
+                highlight: entry.overdue(),
+            })
+            .collect();
+        blocks.push(Block::Table(
+            vec!["State", "Item", "Owner", "Deadline", "Location"],
+            rows,
+        ));
+    }
+
+    blocks.push(Block::Heading(2, "By month".to_string()));
+    // The warning and expiry dates, by the first day of their month.
+    let mut events: BTreeMap<NaiveDate, Vec<Event>> = BTreeMap::new();
+    for entry in &entries {
+        // Code that warns and expires at the same time is only listed as expiring.
+        if let Some(warns) = entry.warns.filter(|warns| Some(*warns) != entry.expires) {
+            events
+                .entry(warns.with_day(1).unwrap_or(warns))
+                .or_default()
+                .push((warns, "warns", entry));
+        }
+        if let Some(expires) = entry.expires {
+            events
+                .entry(expires.with_day(1).unwrap_or(expires))
+                .or_default()
+                .push((expires, "expires", entry));
+        }
+    }
+    for (month, mut month_events) in events {
+        month_events.sort_by_key(|(date, _, _)| *date);
+        blocks.push(Block::Heading(3, month.format("%B %Y").to_string()));
+        let rows = month_events
+            .iter()
+            .map(|(date, event, entry)| Row {
+                cells: vec![
+                    event.to_string(),
+                    date.format("%Y-%m-%d").to_string(),
+                    entry.item(),
+                    entry.owner.to_string(),
+                    entry.finding.outcome.verdict.state().to_string(),
+                    entry.finding.position(),
+                ],
+                highlight: entry.overdue(),
+            })
+            .collect();
+        blocks.push(Block::Table(
+            vec!["Event", "Last day", "Item", "Owner", "State", "Location"],
+            rows,
+        ));
+    }
+
+    blocks.push(Block::Heading(2, "By owner".to_string()));
+    blocks.push(summary_table("Owner", &entries, |entry| {
+        entry.owner.to_string()
+    }));
+    blocks.push(Block::Heading(2, "By module".to_string()));
+    blocks.push(summary_table("Module", &entries, |entry| {
+        entry.module.clone()
+    }));
+    blocks
+}
+
+/// A table with the number of annotations per `key`, highlighting keys with overdue code.
+fn summary_table(title: &'static str, entries: &[Entry], key: impl Fn(&Entry) -> String) -> Block {
+    let mut groups: BTreeMap<String, Vec<&Entry>> = BTreeMap::new();
+    for entry in entries {
+        groups.entry(key(entry)).or_default().push(entry);
+    }
+    let rows = groups
+        .into_iter()
+        .map(|(name, entries)| {
+            let count = |state| {
+                entries
+                    .iter()
+                    .filter(|entry| entry.overdue() == Some(state))
+                    .count()
+            };
+            let (expired, warning) = (count("expired"), count("warning"));
+            let next = entries
+                .iter()
+                .map(|entry| entry.finding.deadline())
+                .min()
+                .map_or_else(String::new, |date| date.format("%Y-%m-%d").to_string());
+            Row {
+                cells: vec![
+                    name,
+                    entries.len().to_string(),
+                    expired.to_string(),
+                    warning.to_string(),
+                    next,
+                ],
+                highlight: if expired > 0 {
+                    Some("expired")
+                } else if warning > 0 {
+                    Some("warning")
+                } else {
+                    None
+                },
+            }
+        })
+        .collect();
+    Block::Table(
+        vec![
+            title,
+            "Annotations",
+            "Expired",
+            "Warning",
+            "Earliest deadline",
+        ],
+        rows,
+    )
+}
+
+/// For every month from the first date to the last (and today), its last day and how many
+/// annotations are still in date and not expired on that day.
+fn burndown(entries: &[Entry], today: NaiveDate) -> Vec<(NaiveDate, usize, usize)> {
+    let dates = entries
+        .iter()
+        .flat_map(|entry| [entry.warns, entry.expires])
+        .flatten()
+        .chain([today]);
+    let first = dates.clone().min().unwrap_or(today);
+    let last = dates.max().unwrap_or(today);
+
+    let mut months = Vec::new();
+    let mut month = (first.year(), first.month());
+    while month <= (last.year(), last.month()) {
+        let next = if month.1 == 12 {
+            (month.0 + 1, 1)
+        } else {
+            (month.0, month.1 + 1)
+        };
+        let Some(end) = NaiveDate::from_ymd_opt(next.0, next.1, 1).and_then(|date| date.pred_opt())
+        else {
+            break;
+        };
+        let remaining = |date: Option<NaiveDate>| date.is_none_or(|date| date > end);
+        months.push((
+            end,
+            entries
+                .iter()
+                .filter(|entry| remaining(entry.warns))
+                .count(),
+            entries
+                .iter()
+                .filter(|entry| remaining(entry.expires))
+                .count(),
+        ));
+        month = next;
+    }
+    months
+}
+
+/// The months of the burndown within [`CHART_MONTHS`] of today.
+fn chart_window(
+    months: &[(NaiveDate, usize, usize)],
+    today: NaiveDate,
+) -> &[(NaiveDate, usize, usize)] {
+    let current = months
+        .iter()
+        .position(|(end, _, _)| *end >= today)
+        .unwrap_or_default();
+    let start = current.saturating_sub(CHART_MONTHS.0);
+    let end = (current + CHART_MONTHS.1 + 1).min(months.len());
+    &months[start..end]
+}
+
+/// The burndown as an SVG line chart, with a marker for today.
+fn svg(months: &[(NaiveDate, usize, usize)], total: usize, today: NaiveDate) -> String {
+    const WIDTH: f64 = 720.0;
+    const HEIGHT: f64 = 300.0;
+    const LEFT: f64 = 48.0;
+    const RIGHT: f64 = 16.0;
+    const TOP: f64 = 16.0;
+    const BOTTOM: f64 = 56.0;
+    let plot_width = WIDTH - LEFT - RIGHT;
+    let plot_height = HEIGHT - TOP - BOTTOM;
+    let steps = months.len().saturating_sub(1).max(1) as f64;
+    let x = |index: usize| LEFT + plot_width * index as f64 / steps;
+    let y = |value: usize| TOP + plot_height * (1.0 - value as f64 / total.max(1) as f64);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\" font-family=\"sans-serif\" font-size=\"11\">",
+        WIDTH, HEIGHT
+    );
+    svg.push_str(&format!(
+        "<line x1=\"{0}\" y1=\"{1}\" x2=\"{0}\" y2=\"{2}\" stroke=\"#888\"/><line x1=\"{0}\" y1=\"{2}\" x2=\"{3}\" y2=\"{2}\" stroke=\"#888\"/>",
+        LEFT,
+        TOP,
+        TOP + plot_height,
+        LEFT + plot_width
+    ));
+    for value in [0, total / 2, total] {
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            LEFT - 6.0,
+            y(value) + 4.0,
+            value
+        ));
+    }
+    // At most a dozen labels on the time axis.
+    let label_every = months.len().div_ceil(12).max(1);
+    for (index, (month, _, _)) in months.iter().enumerate() {
+        if index % label_every == 0 {
+            svg.push_str(&format!(
+                "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>",
+                x(index),
+                TOP + plot_height + 16.0,
+                month.format("%Y-%m")
+            ));
+        }
+    }
+    if let Some(index) = months.iter().position(|(end, _, _)| *end >= today) {
+        svg.push_str(&format!(
+            "<line x1=\"{0:.1}\" y1=\"{1}\" x2=\"{0:.1}\" y2=\"{2}\" stroke=\"#555\" stroke-dasharray=\"4 3\"/><text x=\"{0:.1}\" y=\"{3}\" text-anchor=\"middle\">today</text>",
+            x(index),
+            TOP,
+            TOP + plot_height,
+            TOP - 4.0
+        ));
+    }
+    for (series, color, label) in [(1, "#2f7ed8", "In date"), (2, "#d9534f", "Not expired")] {
+        // A step line, with points only where the value changes.
+        let values = months
+            .iter()
+            .map(|month| if series == 1 { month.1 } else { month.2 })
+            .collect::<Vec<_>>();
+        let mut points = vec![(x(0), y(values[0]))];
+        for (index, pair) in values.windows(2).enumerate() {
+            if pair[0] != pair[1] {
+                points.push((x(index + 1), y(pair[0])));
+                points.push((x(index + 1), y(pair[1])));
+            }
+        }
+        points.push((x(values.len() - 1), y(values[values.len() - 1])));
+        let points = points
+            .iter()
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect::<Vec<_>>()
+            .join(" ");
+        svg.push_str(&format!(
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"2\" points=\"{}\"/>",
+            color, points
+        ));
+        let legend_x = LEFT + (series - 1) as f64 * 120.0;
+        svg.push_str(&format!(
+            "<rect x=\"{:.1}\" y=\"{}\" width=\"12\" height=\"12\" fill=\"{}\"/><text x=\"{:.1}\" y=\"{}\">{}</text>",
+            legend_x,
+            HEIGHT - 20.0,
+            color,
+            legend_x + 16.0,
+            HEIGHT - 10.0,
+            label
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn escapes_markdown_cells() {
+        assert_eq!(
+            markdown_cell("R&D <ops> | \"rates\"\tnow"),
+            "R&amp;D &lt;ops&gt; \\| \"rates\" now"
+        );
+    }
+
+    #[test]
+    fn charts_the_months_around_today() {
+        let months = (2025..2100)
+            .flat_map(|year| (1..=12).map(move |month| (year, month)))
+            .map(|(year, month)| {
+                let end = NaiveDate::from_ymd_opt(year, month, 1)
+                    .and_then(|first| first.checked_add_months(chrono::Months::new(1)))
+                    .and_then(|next| next.pred_opt())
+                    .unwrap();
+                (end, 1, 1)
+            })
+            .collect::<Vec<_>>();
+        let window = chart_window(&months, day("2025-07-01"));
+        assert_eq!(window.first().unwrap().0, day("2025-01-31"));
+        assert_eq!(window.last().unwrap().0, day("2027-07-31"));
+        let window = chart_window(&months, day("2060-03-15"));
+        assert_eq!(window.first().unwrap().0, day("2059-03-31"));
+        assert_eq!(window.len(), 37);
+        let window = chart_window(&months, day("2099-12-01"));
+        assert_eq!(window.last().unwrap().0, day("2099-12-31"));
+    }
+}
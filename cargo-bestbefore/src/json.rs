@@ -0,0 +1,314 @@
+//! Building JSON documents for the output formats that nest deeper than a report record, and
+//! reading those of cargo.
+
+use bestbefore_core::report;
+
+/// A JSON value.
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    /// An object, with its fields in the given order.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// An object with the fields `fields`, leaving out those that are `null`.
+    pub(crate) fn object<'a>(fields: impl IntoIterator<Item = (&'a str, Json)>) -> Self {
+        Json::Object(
+            fields
+                .into_iter()
+                .filter(|(_, value)| !matches!(value, Json::Null))
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        )
+    }
+
+    /// Parses a JSON document.
+    ///
+    /// Numbers that are not integers are read as `null`, as nothing read so far has them.
+    pub(crate) fn parse(text: &str) -> Result<Self, String> {
+        let mut reader = Reader { text, position: 0 };
+        let value = reader.value()?;
+        reader.skip_whitespace();
+        if reader.position < text.len() {
+            return Err(reader.error("expected the end of the document"));
+        }
+        Ok(value)
+    }
+
+    /// The field `name`, if this is an object that has it.
+    pub(crate) fn get(&self, name: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The elements, if this is an array, or else none.
+    pub(crate) fn elements(&self) -> &[Json] {
+        match self {
+            Json::Array(values) => values,
+            _ => &[],
+        }
+    }
+
+    /// Renders the value indented by two spaces per level.
+    pub(crate) fn pretty(&self) -> String {
+        let mut json = String::new();
+        self.write(&mut json, 0);
+        json.push('\n');
+        json
+    }
+
+    fn write(&self, json: &mut String, depth: usize) {
+        let indent = |json: &mut String, depth: usize| {
+            json.push('\n');
+            json.push_str(&"  ".repeat(depth));
+        };
+        match self {
+            Json::Null => json.push_str("null"),
+            Json::Bool(value) => json.push_str(if *value { "true" } else { "false" }),
+            Json::Number(value) => json.push_str(&value.to_string()),
+            Json::String(value) => json.push_str(&report::string(value)),
+            Json::Array(values) if values.is_empty() => json.push_str("[]"),
+            Json::Array(values) => {
+                json.push('[');
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        json.push(',');
+                    }
+                    indent(json, depth + 1);
+                    value.write(json, depth + 1);
+                }
+                indent(json, depth);
+                json.push(']');
+            }
+            Json::Object(fields) if fields.is_empty() => json.push_str("{}"),
+            Json::Object(fields) => {
+                json.push('{');
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        json.push(',');
+                    }
+                    indent(json, depth + 1);
+                    json.push_str(&report::string(name));
+                    json.push_str(": ");
+                    value.write(json, depth + 1);
+                }
+                indent(json, depth);
+                json.push('}');
+            }
+        }
+    }
+}
+
+/// Reads a JSON document from `text`, starting at `position`.
+struct Reader<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("Invalid JSON at byte {}: {}", self.position, message)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    /// Skips `byte`, which may be preceded by whitespace.
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(self.error(&format!("expected '{}'", byte as char)));
+        }
+        self.position += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => {
+                self.position += 1;
+                let mut fields = Vec::new();
+                if self.end_of(b'}')? {
+                    return Ok(Json::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let name = self.string()?;
+                    self.expect(b':')?;
+                    fields.push((name, self.value()?));
+                    if self.end_of(b'}')? {
+                        return Ok(Json::Object(fields));
+                    }
+                    self.expect(b',')?;
+                }
+            }
+            Some(b'[') => {
+                self.position += 1;
+                let mut values = Vec::new();
+                if self.end_of(b']')? {
+                    return Ok(Json::Array(values));
+                }
+                loop {
+                    values.push(self.value()?);
+                    if self.end_of(b']')? {
+                        return Ok(Json::Array(values));
+                    }
+                    self.expect(b',')?;
+                }
+            }
+            Some(b'"') => self.string().map(Json::String),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b'-' | b'0'..=b'9') => {
+                let start = self.position;
+                while matches!(
+                    self.peek(),
+                    Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+                ) {
+                    self.position += 1;
+                }
+                Ok(self.text[start..self.position]
+                    .parse()
+                    .map_or(Json::Null, Json::Number))
+            }
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    /// Skips `close` if it is next, returning whether it was.
+    fn end_of(&mut self, close: u8) -> Result<bool, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(byte) if byte == close => {
+                self.position += 1;
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Err(self.error("unexpected end of the document")),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Result<Json, String> {
+        if !self.text[self.position..].starts_with(word) {
+            return Err(self.error("expected a value"));
+        }
+        self.position += word.len();
+        Ok(value)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        if self.peek() != Some(b'"') {
+            return Err(self.error("expected a string"));
+        }
+        self.position += 1;
+        let mut string = String::new();
+        loop {
+            let c = self.text[self.position..]
+                .chars()
+                .next()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.position += c.len_utf8();
+            match c {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escape = self
+                        .peek()
+                        .ok_or_else(|| self.error("unterminated string"))?;
+                    self.position += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    string.push(c);
+                }
+                c => string.push(c),
+            }
+        }
+    }
+
+    /// The character of a `\u` escape, whose `\u` has been read, joining surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if !self.text[self.position..].starts_with("\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            self.position += 2;
+            let low = self.hex()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid escape"))
+    }
+
+    fn hex(&mut self) -> Result<u32, String> {
+        let code = self
+            .text
+            .get(self.position..self.position + 4)
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid escape"))?;
+        self.position += 4;
+        Ok(code)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as i64)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
@@ -0,0 +1,117 @@
+//! JUnit XML output, for CI systems that show test reports.
+//!
+//! Every annotation is a test case: passed while no diagnostic is due, skipped once it warns and
+//! failed once it has expired. Annotations are grouped into a test suite per owner.
+
+use crate::scan::{display_path, Finding};
+use crate::xml::{escape, escape_attribute};
+use bestbefore_core::eval::Verdict;
+use std::collections::BTreeMap;
+
+/// The suite of annotations without an owner.
+const UNASSIGNED: &str = "unassigned";
+
+/// The JUnit report of `findings`.
+pub(crate) fn render(findings: &[Finding]) -> String {
+    // Owners in alphabetical order, followed by the unassigned annotations.
+    let mut suites: BTreeMap<(bool, &str), Vec<&Finding>> = BTreeMap::new();
+    for finding in findings {
+        let owner = finding.args.owner.as_deref();
+        suites
+            .entry((owner.is_none(), owner.unwrap_or(UNASSIGNED)))
+            .or_default()
+            .push(finding);
+    }
+
+    let mut suites_xml = String::new();
+    for ((_, owner), findings) in &suites {
+        let mut cases = String::new();
+        for finding in findings {
+            cases.push_str(&test_case(finding));
+        }
+        let (failures, skipped) = counts(findings.iter().copied());
+        suites_xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\">\n{}  </testsuite>\n",
+            escape_attribute(owner),
+            findings.len(),
+            failures,
+            skipped,
+            cases
+        ));
+    }
+
+    let (failures, skipped) = counts(findings.iter());
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"bestbefore\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\">\n{}</testsuites>\n",
+        findings.len(),
+        failures,
+        skipped,
+        suites_xml
+    )
+}
+
+/// The numbers of failed and skipped test cases among `findings`.
+fn counts<'a>(findings: impl Iterator<Item = &'a Finding<'a>>) -> (usize, usize) {
+    findings.fold((0, 0), |(failures, skipped), finding| {
+        match finding.outcome.verdict {
+            Verdict::Fail { .. } => (failures + 1, skipped),
+            Verdict::Warn { .. } => (failures, skipped + 1),
+            Verdict::Fresh | Verdict::Note { .. } => (failures, skipped),
+        }
+    })
+}
+
+fn test_case(finding: &Finding) -> String {
+    let name = finding
+        .subject
+        .path
+        .clone()
+        .unwrap_or_else(|| format!("{} at {}", finding.subject.name, finding.position()));
+    // Test reports group test cases by class, which is the module here.
+    let classname = finding
+        .subject
+        .location
+        .as_ref()
+        .map(|location| {
+            let mut module = finding.subject.krate.iter().cloned().collect::<Vec<_>>();
+            module.extend(location.module_path());
+            module.join("::")
+        })
+        .unwrap_or_default();
+    let line = finding
+        .subject
+        .location
+        .as_ref()
+        .map_or(0, |location| location.line);
+    let header = format!(
+        "    <testcase name=\"{}\" classname=\"{}\" file=\"{}\" line=\"{}\" time=\"0\"",
+        escape_attribute(&name),
+        escape_attribute(&classname),
+        escape_attribute(&display_path(&finding.file)),
+        line
+    );
+
+    let details = finding
+        .outcome
+        .details
+        .iter()
+        .map(|(label, value)| format!("{}: {}", label, value))
+        .collect::<Vec<_>>()
+        .join("\n");
+    match &finding.outcome.verdict {
+        Verdict::Fresh | Verdict::Note { .. } => format!("{} />\n", header),
+        Verdict::Warn { message, .. } => format!(
+            "{}>\n      <skipped message=\"{}\" />\n      <system-out>{}</system-out>\n    </testcase>\n",
+            header,
+            escape_attribute(message),
+            escape(&details)
+        ),
+        Verdict::Fail { message, .. } => format!(
+            "{}>\n      <failure message=\"{}\" type=\"expired\">{}\n{}</failure>\n    </testcase>\n",
+            header,
+            escape_attribute(message),
+            escape(message),
+            escape(&details)
+        ),
+    }
+}
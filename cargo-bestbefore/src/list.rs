@@ -0,0 +1,94 @@
+//! `cargo bestbefore list`: every annotation, nearest deadline first.
+
+use crate::scan::Finding;
+use crate::Format;
+
+/// Prints `findings` in `format`.
+pub(crate) fn run(mut findings: Vec<Finding>, format: Format) {
+    findings.sort_by_cached_key(|finding| {
+        (
+            finding.deadline(),
+            finding.file.clone(),
+            finding
+                .subject
+                .location
+                .as_ref()
+                .map(|location| location.line),
+        )
+    });
+    match format {
+        Format::Json => print!("{}", json(&findings)),
+        _ => print!("{}", table(&findings)),
+    }
+}
+
+/// One line per annotation, in aligned columns.
+fn table(findings: &[Finding]) -> String {
+    let mut rows = vec![[
+        "DEADLINE".to_string(),
+        "STATE".to_string(),
+        "ITEM".to_string(),
+        "PATH".to_string(),
+        "DATES".to_string(),
+        "LOCATION".to_string(),
+    ]];
+    for finding in findings {
+        let dates = finding
+            .args
+            .stages
+            .iter()
+            .map(|stage| format!("{} {}", stage.stage.key(), stage.date.value))
+            .collect::<Vec<_>>()
+            .join(", ");
+        rows.push([
+            finding.deadline().format("%Y-%m-%d").to_string(),
+            finding.outcome.verdict.state().to_string(),
+            finding.subject.name.clone(),
+            finding
+                .subject
+                .path
+                .clone()
+                .unwrap_or_else(|| "-".to_string()),
+            dates,
+            finding.position(),
+        ]);
+    }
+
+    let mut widths = [0; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut table = String::new();
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// A JSON array of records in the format of the report, one per line.
+pub(crate) fn json(findings: &[Finding]) -> String {
+    let records = findings
+        .iter()
+        .map(|finding| {
+            finding
+                .package
+                .context
+                .record(&finding.subject, &finding.args, &finding.outcome)
+                .to_json()
+        })
+        .collect::<Vec<_>>();
+    if records.is_empty() {
+        "[]\n".to_string()
+    } else {
+        format!("[\n{}\n]\n", records.join(",\n"))
+    }
+}
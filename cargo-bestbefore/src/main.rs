@@ -0,0 +1,393 @@
+//! `cargo bestbefore`: works with the `#[bestbefore]` annotations of a workspace without
+//! compiling it.
+//!
+//! The annotations are parsed and evaluated by the same code as in the macro, from
+//! `bestbefore-core`, so the command reports exactly what the compiler would.
+
+mod bump;
+mod calendar;
+mod check;
+mod ci;
+mod debt;
+mod json;
+mod junit;
+mod list;
+mod sarif;
+mod scan;
+mod xml;
+
+use bestbefore_core::clock::Clock;
+use scan::Workspace;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Checks the #[bestbefore] annotations of a workspace without compiling it
+
+Usage: cargo bestbefore <COMMAND> [OPTIONS]
+
+Commands:
+  list      Print every annotation, nearest deadline first
+  check     Report warnings and expired code as the compiler would, for CI
+  debt      Summarize what is due when, by month, owner and module
+  calendar  Print the warning and expiry dates as an iCalendar feed
+  bump      Move the dates of annotations by rewriting them in place
+
+Options:
+      --format <FORMAT>       Output format
+                              list: table (default) or json
+                              check: text (default), json, sarif, junit,
+                                     github or gitlab
+                              debt: markdown (default) or html
+                              calendar: ics (default)
+                              bump: diff (default)
+      --at <DATE>             Evaluate on the first day of DATE instead of today
+      --manifest-path <PATH>  The Cargo.toml of the package or workspace to scan
+      --owner <OWNER>         Only include the annotations of OWNER (repeatable)
+  -h, --help                  Print this help
+  -V, --version               Print the version
+
+Options for bump:
+      --to <DATE>             The new deadline; earlier stages move along
+      --id <ID>               Move the annotation with this id (repeatable)
+      --due <FROM..UNTIL>     Move the annotations with a deadline in this range
+      --write                 Write the changes instead of printing a diff
+      --reason <TEXT>         Record the bump as a [[snooze]] entry with this
+                              reason, together with --approver
+      --approver <NAME>       Who approved the bump
+
+Annotations are evaluated as the compiler would evaluate them, honoring the same environment
+variables and configuration.
+
+Exit status:
+  0  Nothing is due (notes do not count)
+  1  check: there are warnings, but nothing has expired
+  2  check: code has expired
+  3  Invalid usage, configuration or annotations";
+
+/// Exit code for invalid usage, configuration or annotations.
+const EXIT_INVALID: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    List,
+    Check,
+    Debt,
+    Calendar,
+    Bump,
+}
+
+impl Command {
+    /// The output formats of the command, the default first.
+    fn formats(self) -> &'static [Format] {
+        match self {
+            Command::List => &[Format::Table, Format::Json],
+            Command::Check => &[
+                Format::Text,
+                Format::Json,
+                Format::Sarif,
+                Format::Junit,
+                Format::Github,
+                Format::Gitlab,
+            ],
+            Command::Debt => &[Format::Markdown, Format::Html],
+            Command::Calendar => &[Format::Ics],
+            Command::Bump => &[Format::Diff],
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Command::List => "list",
+            Command::Check => "check",
+            Command::Debt => "debt",
+            Command::Calendar => "calendar",
+            Command::Bump => "bump",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Table,
+    Text,
+    Json,
+    Sarif,
+    Junit,
+    /// GitHub Actions workflow commands.
+    Github,
+    /// GitLab Code Quality report.
+    Gitlab,
+    Markdown,
+    /// A self-contained HTML page.
+    Html,
+    /// iCalendar.
+    Ics,
+    /// Unified diff.
+    Diff,
+}
+
+impl Format {
+    const ALL: &'static [Format] = &[
+        Format::Table,
+        Format::Text,
+        Format::Json,
+        Format::Sarif,
+        Format::Junit,
+        Format::Github,
+        Format::Gitlab,
+        Format::Markdown,
+        Format::Html,
+        Format::Ics,
+        Format::Diff,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::Table => "table",
+            Format::Text => "text",
+            Format::Json => "json",
+            Format::Sarif => "sarif",
+            Format::Junit => "junit",
+            Format::Github => "github",
+            Format::Gitlab => "gitlab",
+            Format::Markdown => "markdown",
+            Format::Html => "html",
+            Format::Ics => "ics",
+            Format::Diff => "diff",
+        }
+    }
+}
+
+struct Options {
+    command: Command,
+    format: Format,
+    /// The date given with `--at`.
+    at: Option<String>,
+    manifest_path: Option<PathBuf>,
+    /// The owners given with `--owner`, if the annotations are limited to theirs.
+    owners: Vec<String>,
+    bump: bump::Args,
+}
+
+enum Parsed {
+    Run(Box<Options>),
+    Help,
+    Version,
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Parsed::Run(options)) => options,
+        Ok(Parsed::Help) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Parsed::Version) => {
+            println!("cargo-bestbefore {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("error: {}\n\nFor more information, try '--help'.", message);
+            return ExitCode::from(EXIT_INVALID);
+        }
+    };
+    match run(&options) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::from(EXIT_INVALID)
+        }
+    }
+}
+
+fn run(options: &Options) -> Result<ExitCode, String> {
+    let root = workspace_root(options.manifest_path.as_deref())?;
+    let clock = match &options.at {
+        Some(at) => {
+            Clock::fixed("--at", at).map_err(|message| format!("Invalid --at: {}", message))?
+        }
+        None => Clock::from_env()?,
+    };
+    let workspace = Workspace::open(&root, &clock)?;
+    let (mut findings, problems) = workspace.findings();
+    for warning in workspace.unused_snoozes(&findings) {
+        eprintln!("warning: {}", warning);
+    }
+    if !options.owners.is_empty() {
+        findings.retain(|finding| {
+            finding
+                .args
+                .owner
+                .as_ref()
+                .is_some_and(|owner| options.owners.contains(owner))
+        });
+    }
+    for problem in &problems {
+        eprintln!("error: {}", problem);
+    }
+    let code = match options.command {
+        Command::List => {
+            list::run(findings, options.format);
+            0
+        }
+        Command::Check => check::run(&findings, options.format),
+        Command::Debt => {
+            match options.format {
+                Format::Html => print!("{}", debt::html(&findings, clock.today)),
+                _ => print!("{}", debt::markdown(&findings, clock.today)),
+            }
+            0
+        }
+        Command::Calendar => {
+            print!(
+                "{}",
+                calendar::render(&findings, &options.owners, clock.today)
+            );
+            0
+        }
+        Command::Bump => {
+            bump::run(&workspace.root, &findings, &options.bump)?;
+            0
+        }
+    };
+    Ok(ExitCode::from(if problems.is_empty() {
+        code
+    } else {
+        EXIT_INVALID
+    }))
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Parsed, String> {
+    let mut args = args.peekable();
+    // Cargo passes the name of the subcommand as the first argument.
+    if args.peek().map(String::as_str) == Some("bestbefore") {
+        args.next();
+    }
+
+    let mut command = None;
+    let mut format = None;
+    let mut at = None;
+    let mut manifest_path = None;
+    let mut owners = Vec::new();
+    let mut bump = bump::Args::default();
+    while let Some(arg) = args.next() {
+        let (name, inline) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => (name.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .map(str::to_string)
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{} requires a value", name))
+        };
+        match name.as_str() {
+            "-h" | "--help" => return Ok(Parsed::Help),
+            "-V" | "--version" => return Ok(Parsed::Version),
+            "--format" => {
+                let name = value("--format")?;
+                let found = Format::ALL.iter().find(|format| format.name() == name);
+                format = Some(*found.ok_or_else(|| format!("Unknown format '{}'", name))?);
+            }
+            "--at" => at = Some(value("--at")?),
+            "--manifest-path" => manifest_path = Some(PathBuf::from(value("--manifest-path")?)),
+            "--owner" => owners.push(value("--owner")?),
+            "--to" => bump.to = Some(value("--to")?),
+            "--id" => bump.ids.push(value("--id")?),
+            "--due" => bump.due = Some(value("--due")?),
+            "--write" => bump.write = true,
+            "--reason" => bump.reason = Some(value("--reason")?),
+            "--approver" => bump.approver = Some(value("--approver")?),
+            "list" if command.is_none() => command = Some(Command::List),
+            "check" if command.is_none() => command = Some(Command::Check),
+            "debt" if command.is_none() => command = Some(Command::Debt),
+            "calendar" if command.is_none() => command = Some(Command::Calendar),
+            "bump" if command.is_none() => command = Some(Command::Bump),
+            other if other.starts_with('-') => return Err(format!("Unknown option '{}'", other)),
+            other => return Err(format!("Unknown command '{}'", other)),
+        }
+    }
+
+    let command = command.ok_or("Missing command")?;
+    let formats = command.formats();
+    let format = format.unwrap_or(formats[0]);
+    if !formats.contains(&format) {
+        let names = formats
+            .iter()
+            .map(|format| format.name())
+            .collect::<Vec<_>>();
+        return Err(format!(
+            "{} does not support the {} format, expected one of: {}",
+            command.name(),
+            format.name(),
+            names.join(", ")
+        ));
+    }
+    if command == Command::Bump {
+        if bump.to.is_none() {
+            return Err("bump requires --to".to_string());
+        }
+        if bump.ids.is_empty() && bump.due.is_none() && owners.is_empty() {
+            return Err("bump requires --id, --owner or --due to select annotations".to_string());
+        }
+        if bump.reason.is_some() != bump.approver.is_some() {
+            return Err("--reason and --approver are only accepted together".to_string());
+        }
+    } else if bump != bump::Args::default() {
+        return Err(format!(
+            "{} does not accept --to, --id, --due, --write, --reason or --approver",
+            command.name()
+        ));
+    }
+    Ok(Parsed::Run(Box::new(Options {
+        command,
+        format,
+        at,
+        manifest_path,
+        owners,
+        bump,
+    })))
+}
+
+/// The directory to scan: that of `manifest_path`, or else the workspace (or package) around
+/// the working directory, as found by cargo.
+fn workspace_root(manifest_path: Option<&Path>) -> Result<PathBuf, String> {
+    if let Some(manifest_path) = manifest_path {
+        if !manifest_path.is_file() {
+            return Err(format!(
+                "Manifest path {} does not exist",
+                manifest_path.display()
+            ));
+        }
+        return Ok(match manifest_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        });
+    }
+
+    let cwd = env::current_dir().map_err(|err| format!("Cannot get working directory: {}", err))?;
+    let mut package = None;
+    for dir in cwd.ancestors() {
+        let manifest = dir.join("Cargo.toml");
+        if !manifest.is_file() {
+            continue;
+        }
+        package.get_or_insert_with(|| dir.to_path_buf());
+        let is_workspace = fs::read_to_string(&manifest)
+            .ok()
+            .and_then(|text| text.parse::<toml::Table>().ok())
+            .is_some_and(|table| table.contains_key("workspace"));
+        if is_workspace {
+            return Ok(dir.to_path_buf());
+        }
+    }
+    package.ok_or_else(|| {
+        format!(
+            "Could not find Cargo.toml in {} or any parent directory",
+            cwd.display()
+        )
+    })
+}
@@ -0,0 +1,166 @@
+//! SARIF 2.1.0 output, for code scanning dashboards.
+//!
+//! Every annotation that produces a warning or an error is a result, located at the attribute
+//! or macro invocation. Each stage that produces diagnostics is a rule of its own.
+
+use crate::json::Json;
+use crate::scan::{display_path, Finding};
+use bestbefore_core::args::Stage;
+use bestbefore_core::eval::Verdict;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The stages with diagnostics, with the description of their rule.
+const RULES: &[(Stage, &str)] = &[
+    (Stage::Warn, "Code is past its best-before date"),
+    (
+        Stage::DenyInCi,
+        "Code is past its best-before date and fails CI builds",
+    ),
+    (Stage::Error, "Code has expired and fails the build"),
+];
+
+/// The SARIF log of `findings`.
+pub(crate) fn render(findings: &[Finding]) -> String {
+    let rules = RULES
+        .iter()
+        .map(|(stage, description)| {
+            Json::object([
+                ("id", rule_id(*stage).into()),
+                ("name", stage.key().into()),
+                (
+                    "shortDescription",
+                    Json::object([("text", (*description).into())]),
+                ),
+                (
+                    "defaultConfiguration",
+                    Json::object([("level", level(*stage).into())]),
+                ),
+            ])
+        })
+        .collect();
+    let results = findings.iter().filter_map(result).collect();
+
+    Json::object([
+        ("$schema", SCHEMA.into()),
+        ("version", "2.1.0".into()),
+        (
+            "runs",
+            Json::Array(vec![Json::object([
+                (
+                    "tool",
+                    Json::object([(
+                        "driver",
+                        Json::object([
+                            ("name", "cargo-bestbefore".into()),
+                            ("version", env!("CARGO_PKG_VERSION").into()),
+                            ("informationUri", env!("CARGO_PKG_REPOSITORY").into()),
+                            ("rules", Json::Array(rules)),
+                        ]),
+                    )]),
+                ),
+                ("results", Json::Array(results)),
+            ])]),
+        ),
+    ])
+    .pretty()
+}
+
+/// The result for `finding`, if it produces a diagnostic.
+fn result(finding: &Finding) -> Option<Json> {
+    let (level, message) = match &finding.outcome.verdict {
+        Verdict::Warn { message, .. } => ("warning", message),
+        Verdict::Fail { message, .. } => ("error", message),
+        Verdict::Fresh | Verdict::Note { .. } => return None,
+    };
+    // The mode can turn the error stage into a warning and the reverse, so the rule follows the
+    // stage and the level the outcome.
+    let stage = finding.stage().unwrap_or(Stage::Warn);
+    let rule_index = RULES
+        .iter()
+        .position(|(rule, _)| *rule == stage)
+        .unwrap_or(0);
+    let location = finding.subject.location.as_ref()?;
+    let (end_line, end_column) = finding.end;
+
+    let detail = |label: &str| {
+        finding
+            .outcome
+            .details
+            .iter()
+            .find(|(name, _)| *name == label)
+            .map(|(_, value)| value.clone())
+    };
+    let properties = Json::object([
+        ("item", finding.subject.name.as_str().into()),
+        ("itemPath", finding.subject.path.clone().into()),
+        ("annotationId", finding.args.id.clone().into()),
+        ("owner", finding.args.owner.clone().into()),
+        ("ticket", detail("ticket").into()),
+        (
+            "deadline",
+            finding.deadline().format("%Y-%m-%d").to_string().into(),
+        ),
+        ("snoozed", detail("snoozed").into()),
+    ]);
+
+    Some(Json::object([
+        ("ruleId", rule_id(RULES[rule_index].0).into()),
+        ("ruleIndex", rule_index.into()),
+        ("level", level.into()),
+        ("message", Json::object([("text", message.as_str().into())])),
+        (
+            "locations",
+            Json::Array(vec![Json::object([(
+                "physicalLocation",
+                Json::object([
+                    (
+                        "artifactLocation",
+                        Json::object([("uri", uri(&display_path(&finding.file)).into())]),
+                    ),
+                    (
+                        "region",
+                        Json::object([
+                            ("startLine", location.line.into()),
+                            ("startColumn", location.column.into()),
+                            ("endLine", end_line.into()),
+                            ("endColumn", end_column.into()),
+                        ]),
+                    ),
+                ]),
+            )])]),
+        ),
+        (
+            "partialFingerprints",
+            Json::object([("bestbefore/v1", finding.fingerprint().into())]),
+        ),
+        ("properties", properties),
+    ]))
+}
+
+/// The rule of `stage`, which other formats use as the name of the check as well.
+pub(crate) fn rule_id(stage: Stage) -> String {
+    format!("bestbefore/{}", stage.key())
+}
+
+/// The level of the diagnostics of `stage` in normal mode.
+fn level(stage: Stage) -> &'static str {
+    match stage {
+        Stage::Error => "error",
+        _ => "warning",
+    }
+}
+
+/// A relative path as a URI reference, with the characters escaped that URIs do not allow.
+fn uri(path: &str) -> String {
+    let mut uri = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
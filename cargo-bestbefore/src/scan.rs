@@ -0,0 +1,760 @@
+//! Finding and evaluating the annotations of a workspace without compiling it.
+//!
+//! Source files are parsed with `syn` and searched for the attributes and macro invocations
+//! the compiler would expand. Arguments are parsed and evaluated by the same code as in the
+//! macro; the item paths are derived from the source the same way as well.
+
+use crate::json::Json;
+use bestbefore_core::args::{BestBeforeArgs, BlockArgs, Stage};
+use bestbefore_core::clock::Clock;
+use bestbefore_core::config::{Config, SnoozeKey};
+use bestbefore_core::context::{Context, Outcome, Subject};
+use bestbefore_core::location::{self, Location};
+use bestbefore_core::target::{self, Target};
+use chrono::NaiveDate;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::ToTokens;
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::ptr;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+/// A package, with what its annotations are evaluated with.
+pub(crate) struct Package {
+    /// The package root, relative to the workspace root.
+    pub(crate) dir: PathBuf,
+    pub(crate) name: String,
+    pub(crate) context: Context,
+    /// The roots of the package's crates.
+    crates: Vec<CrateRoot>,
+}
+
+/// The root file of one of the crates of a package, as cargo reports its targets.
+struct CrateRoot {
+    /// The file, relative to the package root.
+    file: PathBuf,
+    /// The name of the crate, as `CARGO_CRATE_NAME` gives it to the macro.
+    name: String,
+    /// Whether the crate is a library, which is the one modules next to several roots belong to.
+    library: bool,
+    /// Whether other files can belong to the crate, which is not the case for build scripts.
+    has_modules: bool,
+}
+
+/// The packages below a directory and their source files.
+pub(crate) struct Workspace {
+    pub(crate) root: PathBuf,
+    pub(crate) packages: Vec<Package>,
+    /// The source files, relative to the root, with the index of their package.
+    files: Vec<(PathBuf, usize)>,
+}
+
+/// An evaluated annotation.
+pub(crate) struct Finding<'a> {
+    pub(crate) package: &'a Package,
+    /// The source file, relative to the workspace root.
+    pub(crate) file: PathBuf,
+    /// Where the annotation ends, as 1-based line and column just past its last character.
+    /// It starts at the location of the subject.
+    pub(crate) end: (usize, usize),
+    pub(crate) subject: Subject,
+    /// Identifies the annotation across runs, see [`Workspace::findings`].
+    pub(crate) identity: String,
+    /// The arguments, completed from the configuration.
+    pub(crate) args: BestBeforeArgs,
+    pub(crate) outcome: Outcome<'a>,
+}
+
+/// An annotation or source file that cannot be evaluated, which fails the build as well.
+pub(crate) struct Problem {
+    /// The source file, relative to the workspace root.
+    pub(crate) file: PathBuf,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) message: String,
+}
+
+impl Workspace {
+    /// Finds the packages and source files below `root`, to be evaluated on the day of `clock`.
+    ///
+    /// The packages are the members of the workspace `root` is in, as reported by cargo.
+    pub(crate) fn open(root: &Path, clock: &Clock) -> Result<Self, String> {
+        let mut packages = Vec::new();
+        let mut files = Vec::new();
+        for (dir, name, crates) in members(root)? {
+            let config = Config::discover_from(&root.join(&dir))?;
+            walk(root, &dir, &mut |file| files.push((file, packages.len())))?;
+            packages.push(Package {
+                dir,
+                name,
+                context: Context::new(config, clock.clone())?,
+                crates,
+            });
+        }
+        Ok(Workspace {
+            root: root.to_path_buf(),
+            packages,
+            files,
+        })
+    }
+
+    /// Evaluates every annotation in the workspace, in the order of the sources.
+    ///
+    /// Each annotation gets an identity that does not change as code around it is edited: its
+    /// crate and `id`, or else its file, the items it is in and the code's name. Only several
+    /// annotations of the same code, such as blocks in one function, are numbered in the order
+    /// of the sources.
+    pub(crate) fn findings(&self) -> (Vec<Finding<'_>>, Vec<Problem>) {
+        let mut findings = Vec::new();
+        let mut problems = Vec::new();
+        let mut identities: HashMap<String, usize> = HashMap::new();
+        for (file, index) in &self.files {
+            let package = &self.packages[*index];
+            let in_package = file.strip_prefix(&package.dir).unwrap_or(file);
+            let source = match fs::read_to_string(self.root.join(file)) {
+                Ok(source) => source,
+                Err(err) => {
+                    problems.push(Problem {
+                        file: file.clone(),
+                        line: 1,
+                        column: 1,
+                        message: format!("Cannot read file: {}", err),
+                    });
+                    continue;
+                }
+            };
+            let syntax = match syn::parse_file(&source) {
+                Ok(syntax) => syntax,
+                // Files without annotations do not matter, whether or not they are even compiled,
+                // as are test fixtures that are broken on purpose.
+                Err(_) if !source.contains("bestbefore") => continue,
+                Err(err) => {
+                    let start = err.span().start();
+                    problems.push(Problem {
+                        file: file.clone(),
+                        line: start.line,
+                        column: start.column + 1,
+                        message: err.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let krate = package.crate_name(in_package);
+            let mut visitor = Visitor {
+                krate: &krate,
+                file: in_package,
+                source: &source,
+                scope: Vec::new(),
+                found: Vec::new(),
+            };
+            visitor.visit_file(&syntax);
+
+            for found in visitor.found {
+                let context = &package.context;
+                let assessed = found.args.and_then(|mut args| {
+                    let outcome = context.assess(&mut args, &found.subject)?;
+                    Ok((args, outcome))
+                });
+                match assessed {
+                    Ok((args, outcome)) => {
+                        let identity = match &args.id {
+                            Some(id) => format!("{}#{}", krate, id),
+                            None => format!(
+                                "{}:{}:{}{}",
+                                krate,
+                                display_path(in_package),
+                                found.scope,
+                                found.subject.name
+                            ),
+                        };
+                        let seen = identities.entry(identity.clone()).or_default();
+                        *seen += 1;
+                        let identity = match *seen {
+                            1 => identity,
+                            n => format!("{}#{}", identity, n),
+                        };
+                        findings.push(Finding {
+                            package,
+                            file: file.clone(),
+                            end: (found.span.end().line, found.span.end().column + 1),
+                            subject: found.subject,
+                            identity,
+                            args,
+                            outcome,
+                        });
+                    }
+                    Err(err) => problems.extend(err.into_iter().map(|err| {
+                        // Errors without a location of their own are reported at the annotation.
+                        let span = err.span();
+                        let span = if span.start() >= found.span.start()
+                            && span.end() <= found.span.end()
+                        {
+                            span
+                        } else {
+                            found.span
+                        };
+                        Problem {
+                            file: file.clone(),
+                            line: span.start().line,
+                            column: span.start().column + 1,
+                            message: err.to_string(),
+                        }
+                    })),
+                }
+            }
+        }
+        problems.extend(ambiguous_snoozes(&findings));
+        (findings, problems)
+    }
+
+    /// A warning for each snooze entry that matches none of `findings`, such as after the code
+    /// was removed or renamed.
+    pub(crate) fn unused_snoozes(&self, findings: &[Finding]) -> Vec<String> {
+        let mut warnings = Vec::new();
+        // Packages can share a configuration, whose entries match in any of them.
+        let mut origins = Vec::new();
+        for package in &self.packages {
+            let config = &package.context.config;
+            let origin = config.origin();
+            if origins.contains(&origin) {
+                continue;
+            }
+            for snooze in &config.snoozes {
+                let used = findings.iter().any(|finding| {
+                    finding.package.context.config.origin() == origin
+                        && finding
+                            .outcome
+                            .snooze
+                            .is_some_and(|other| other.key == snooze.key)
+                });
+                if !used {
+                    warnings.push(format!(
+                        "The snooze of {} in {} matches no annotation",
+                        snooze.key, origin
+                    ));
+                }
+            }
+            origins.push(origin);
+        }
+        warnings
+    }
+}
+
+/// A problem at each annotation that shares an item snooze with others.
+///
+/// Item paths leave out the types of impl blocks, so different items may have the same one, and
+/// a snooze meant for one of them would silently apply to all.
+fn ambiguous_snoozes(findings: &[Finding]) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    for finding in findings {
+        let Some(snooze) = finding.outcome.snooze else {
+            continue;
+        };
+        let SnoozeKey::Item(path) = &snooze.key else {
+            continue;
+        };
+        let matches = findings
+            .iter()
+            .filter(|other| {
+                other
+                    .outcome
+                    .snooze
+                    .is_some_and(|other| ptr::eq(other, snooze))
+            })
+            .count();
+        if matches > 1 {
+            let location = finding.subject.location.as_ref();
+            problems.push(Problem {
+                file: finding.file.clone(),
+                line: location.map_or(1, |location| location.line),
+                column: location.map_or(1, |location| location.column),
+                message: format!(
+                    "The snooze of item `{}` in {} matches {} annotations; give this one an `id` \
+                     and snooze that instead",
+                    path,
+                    finding.package.context.config.origin(),
+                    matches
+                ),
+            });
+        }
+    }
+    problems
+}
+
+impl Finding<'_> {
+    /// The stage that has been reached, if any.
+    pub(crate) fn stage(&self) -> Option<Stage> {
+        self.args
+            .active_stage(self.package.context.clock.today)
+            .map(|active| active.stage)
+    }
+
+    /// A fingerprint of [`Finding::identity`], as 16 hexadecimal digits.
+    ///
+    /// This is the 64-bit FNV-1a hash, which is stable across platforms and versions.
+    pub(crate) fn fingerprint(&self) -> String {
+        let hash = self
+            .identity
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+            });
+        format!("{:016x}", hash)
+    }
+
+    /// The last day of the final stage, after which the code is due to be gone.
+    pub(crate) fn deadline(&self) -> NaiveDate {
+        self.args
+            .stages
+            .last()
+            .map_or(NaiveDate::MAX, |stage| stage.date.value.end())
+    }
+
+    /// The location of the annotation in the workspace, as `file:line:column`.
+    pub(crate) fn position(&self) -> String {
+        let (line, column) = self
+            .subject
+            .location
+            .as_ref()
+            .map_or((0, 0), |location| (location.line, location.column));
+        format!("{}:{}:{}", display_path(&self.file), line, column)
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            display_path(&self.file),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
+
+/// A relative path with forward slashes, as shown in output on every platform.
+pub(crate) fn display_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/")
+}
+
+/// The member packages of the workspace around `root` that are below `root`, as their
+/// directory relative to `root`, name and crates.
+fn members(root: &Path) -> Result<Vec<(PathBuf, String, Vec<CrateRoot>)>, String> {
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let manifest = root.join("Cargo.toml");
+    let output = Command::new(cargo)
+        .args([
+            "metadata",
+            "--format-version",
+            "1",
+            "--no-deps",
+            "--manifest-path",
+        ])
+        .arg(&manifest)
+        .output()
+        .map_err(|err| format!("Cannot run cargo metadata: {}", err))?;
+    if !output.status.success() {
+        return Err(format!(
+            "cargo metadata failed for {}: {}",
+            manifest.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let metadata = Json::parse(&String::from_utf8_lossy(&output.stdout))
+        .map_err(|message| format!("Cannot read the output of cargo metadata: {}", message))?;
+
+    let canonical = |path: &Path| {
+        fs::canonicalize(path).map_err(|err| format!("Cannot read {}: {}", path.display(), err))
+    };
+    let root = canonical(root)?;
+    let mut members = Vec::new();
+    let packages = metadata.get("packages").map_or(&[][..], Json::elements);
+    for package in packages {
+        let (Some(name), Some(manifest_path)) = (
+            package.get("name").and_then(Json::as_str),
+            package.get("manifest_path").and_then(Json::as_str),
+        ) else {
+            continue;
+        };
+        let Some(dir) = Path::new(manifest_path).parent() else {
+            continue;
+        };
+        let dir = canonical(dir)?;
+        let Ok(relative) = dir.strip_prefix(&root) else {
+            continue;
+        };
+
+        let mut crates = Vec::new();
+        let targets = package.get("targets").map_or(&[][..], Json::elements);
+        for target in targets {
+            let (Some(name), Some(src_path)) = (
+                target.get("name").and_then(Json::as_str),
+                target.get("src_path").and_then(Json::as_str),
+            ) else {
+                continue;
+            };
+            let kinds = target
+                .get("kind")
+                .map_or(&[][..], Json::elements)
+                .iter()
+                .filter_map(Json::as_str)
+                .collect::<Vec<_>>();
+            let Ok(file) = canonical(Path::new(src_path)) else {
+                continue;
+            };
+            let Ok(file) = file.strip_prefix(&dir) else {
+                continue;
+            };
+            crates.push(CrateRoot {
+                file: file.to_path_buf(),
+                name: name.replace('-', "_"),
+                library: !kinds
+                    .iter()
+                    .any(|kind| matches!(*kind, "bin" | "example" | "test" | "bench")),
+                has_modules: !kinds.contains(&"custom-build"),
+            });
+        }
+        members.push((relative.to_path_buf(), name.to_string(), crates));
+    }
+    members.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(members)
+}
+
+/// Hands the source files in `dir` (relative to `root`) to `file`, leaving out those of other
+/// packages inside it.
+fn walk(root: &Path, dir: &Path, file: &mut dyn FnMut(PathBuf)) -> Result<(), String> {
+    let error = |err: std::io::Error| format!("Cannot read {}: {}", root.join(dir).display(), err);
+    let is_package_root = root.join(dir).join("Cargo.toml").is_file();
+    let mut entries = fs::read_dir(root.join(dir))
+        .map_err(error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(error)?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let path = dir.join(name);
+        let file_type = entry.file_type().map_err(error)?;
+        if file_type.is_dir() {
+            // Build output and hidden directories such as .git are not part of the sources.
+            if name.starts_with('.') || (is_package_root && name == "target") {
+                continue;
+            }
+            if root.join(&path).join("Cargo.toml").is_file() {
+                continue;
+            }
+            walk(root, &path, file)?;
+        } else if file_type.is_file() && name.ends_with(".rs") {
+            file(path);
+        }
+    }
+    Ok(())
+}
+
+impl Package {
+    /// The name of the crate `file` belongs to, given its path relative to the package root.
+    ///
+    /// That is the crate rooted in `file`, or else the crate rooted in the closest directory
+    /// above it, preferring the library where binaries are rooted as well, as in `src/`. Other
+    /// files are taken to belong to the library, if there is one.
+    fn crate_name(&self, file: &Path) -> String {
+        let rooted_in = |dir: &Path| {
+            let crates = self
+                .crates
+                .iter()
+                .filter(|root| root.has_modules && root.file.parent() == Some(dir))
+                .collect::<Vec<_>>();
+            crates
+                .iter()
+                .find(|root| root.library)
+                .or(crates.first())
+                .copied()
+        };
+        let root = self
+            .crates
+            .iter()
+            .find(|root| root.file == file)
+            .or_else(|| file.ancestors().skip(1).find_map(rooted_in))
+            .or_else(|| self.crates.iter().find(|root| root.library));
+        match root {
+            Some(root) => root.name.clone(),
+            None => self.name.replace('-', "_"),
+        }
+    }
+}
+
+/// Returns `true` for attributes invoking the `bestbefore` attribute macro.
+fn is_annotation(attr: &syn::Attribute) -> bool {
+    let segments: Vec<String> = attr
+        .path()
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+    segments == ["bestbefore"] || segments == ["bestbefore", "bestbefore"]
+}
+
+/// An annotation found in a file, not evaluated yet.
+struct Found {
+    span: Span,
+    /// The items the annotation is in, such as `mod tests::impl Fee::fn total::`.
+    scope: String,
+    subject: Subject,
+    args: syn::Result<BestBeforeArgs>,
+}
+
+/// Collects the annotations of a file.
+struct Visitor<'a> {
+    krate: &'a str,
+    /// The file, relative to the package root.
+    file: &'a Path,
+    /// The source of the file.
+    source: &'a str,
+    /// The items being visited, outermost first.
+    scope: Vec<String>,
+    found: Vec<Found>,
+}
+
+impl Visitor<'_> {
+    /// Adds the annotation at `span` of the code named `name`, whose path ends in `path_name`.
+    fn add(
+        &mut self,
+        span: Span,
+        name: String,
+        path_name: Option<String>,
+        args: syn::Result<BestBeforeArgs>,
+    ) {
+        let (line, column) = (span.start().line, span.start().column + 1);
+        let location = Location {
+            file: self.file.to_path_buf(),
+            line,
+            column,
+            // Found like the macro does, rather than from the visited items, so that both agree.
+            modules: location::inline_modules(self.source, line, column),
+        };
+        let path =
+            path_name.map(|path_name| location::item_path(Some(self.krate), &location, &path_name));
+        self.found.push(Found {
+            span,
+            scope: self
+                .scope
+                .iter()
+                .map(|item| format!("{}::", item))
+                .collect(),
+            subject: Subject {
+                name,
+                krate: Some(self.krate.to_string()),
+                path,
+                location: Some(location),
+            },
+            args,
+        });
+    }
+
+    /// Visits the contents of the item described as `item` with `visit`.
+    fn within(&mut self, item: String, visit: impl FnOnce(&mut Self)) {
+        self.scope.push(item);
+        visit(self);
+        self.scope.pop();
+    }
+
+    /// Adds the annotations among `attrs`, the attributes of `target`.
+    fn annotations(&mut self, target: Target, attrs: &[syn::Attribute]) {
+        for attr in attrs.iter().filter(|attr| is_annotation(attr)) {
+            // Without arguments on a struct or enum, only its members are annotated.
+            let args = match &attr.meta {
+                syn::Meta::Path(_) if target.has_members() => continue,
+                syn::Meta::List(list) if list.tokens.is_empty() && target.has_members() => continue,
+                syn::Meta::Path(_) => syn::parse2(TokenStream2::new()),
+                _ => attr.parse_args(),
+            };
+            self.add(attr.span(), target.name(), target.path_name(), args);
+        }
+    }
+
+    /// Adds the annotations of the fields and variants of `item`, named as the macro names them.
+    fn members(&mut self, item: &syn::Item) {
+        match item {
+            syn::Item::Struct(item) => {
+                let container = item.ident.to_string();
+                for (index, field) in item.fields.iter().enumerate() {
+                    self.field(field, &container, index);
+                }
+            }
+            syn::Item::Union(item) => {
+                let container = item.ident.to_string();
+                for (index, field) in item.fields.named.iter().enumerate() {
+                    self.field(field, &container, index);
+                }
+            }
+            syn::Item::Enum(item) => {
+                for variant in &item.variants {
+                    let container = format!("{}::{}", item.ident, variant.ident);
+                    for (index, field) in variant.fields.iter().enumerate() {
+                        self.field(field, &container, index);
+                    }
+                    for attr in member_annotations(&variant.attrs) {
+                        self.add(
+                            attr.span(),
+                            format!("variant {}", container),
+                            Some(container.clone()),
+                            attr.parse_args(),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn field(&mut self, field: &syn::Field, container: &str, index: usize) {
+        let path = match &field.ident {
+            Some(ident) => format!("{}::{}", container, ident),
+            None => format!("{}::{}", container, index),
+        };
+        for attr in member_annotations(&field.attrs) {
+            self.add(
+                attr.span(),
+                format!("field {}", path),
+                Some(path.clone()),
+                attr.parse_args(),
+            );
+        }
+    }
+}
+
+/// The target an impl or trait item is to the macro, which parses methods with a body as free
+/// functions.
+fn as_handed_over<T: Clone + ToTokens>(item: &T, target: fn(T) -> Target) -> Target {
+    syn::parse2(item.to_token_stream()).unwrap_or_else(|_| target(item.clone()))
+}
+
+/// The annotations of a field or variant, which are inert attributes named `bestbefore`.
+fn member_annotations(attrs: &[syn::Attribute]) -> impl Iterator<Item = &syn::Attribute> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("bestbefore"))
+}
+
+impl<'ast> Visit<'ast> for Visitor<'_> {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        let attrs = match item {
+            syn::Item::Const(item) => &item.attrs,
+            syn::Item::Enum(item) => &item.attrs,
+            syn::Item::ExternCrate(item) => &item.attrs,
+            syn::Item::Fn(item) => &item.attrs,
+            syn::Item::ForeignMod(item) => &item.attrs,
+            syn::Item::Impl(item) => &item.attrs,
+            syn::Item::Macro(item) => &item.attrs,
+            syn::Item::Mod(item) => &item.attrs,
+            syn::Item::Static(item) => &item.attrs,
+            syn::Item::Struct(item) => &item.attrs,
+            syn::Item::Trait(item) => &item.attrs,
+            syn::Item::TraitAlias(item) => &item.attrs,
+            syn::Item::Type(item) => &item.attrs,
+            syn::Item::Union(item) => &item.attrs,
+            syn::Item::Use(item) => &item.attrs,
+            _ => return visit::visit_item(self, item),
+        };
+        if attrs.iter().any(is_annotation) {
+            self.annotations(Target::Item(item.clone()), attrs);
+            self.members(item);
+        }
+        visit::visit_item(self, item);
+    }
+
+    fn visit_impl_item(&mut self, item: &'ast syn::ImplItem) {
+        let attrs = match item {
+            syn::ImplItem::Const(item) => &item.attrs,
+            syn::ImplItem::Fn(item) => &item.attrs,
+            syn::ImplItem::Type(item) => &item.attrs,
+            syn::ImplItem::Macro(item) => &item.attrs,
+            _ => return visit::visit_impl_item(self, item),
+        };
+        if attrs.iter().any(is_annotation) {
+            self.annotations(as_handed_over(item, Target::ImplItem), attrs);
+        }
+        visit::visit_impl_item(self, item);
+    }
+
+    fn visit_trait_item(&mut self, item: &'ast syn::TraitItem) {
+        let attrs = match item {
+            syn::TraitItem::Const(item) => &item.attrs,
+            syn::TraitItem::Fn(item) => &item.attrs,
+            syn::TraitItem::Type(item) => &item.attrs,
+            syn::TraitItem::Macro(item) => &item.attrs,
+            _ => return visit::visit_trait_item(self, item),
+        };
+        if attrs.iter().any(is_annotation) {
+            self.annotations(as_handed_over(item, Target::TraitItem), attrs);
+        }
+        visit::visit_trait_item(self, item);
+    }
+
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        self.within(format!("mod {}", item.ident), |visitor| {
+            visit::visit_item_mod(visitor, item)
+        });
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        let self_ty = target::render_tokens(&*item.self_ty);
+        let description = match &item.trait_ {
+            Some((_, path, _)) => format!("impl {} for {}", target::render_tokens(path), self_ty),
+            None => format!("impl {}", self_ty),
+        };
+        self.within(description, |visitor| visit::visit_item_impl(visitor, item));
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast syn::ItemTrait) {
+        self.within(format!("trait {}", item.ident), |visitor| {
+            visit::visit_item_trait(visitor, item)
+        });
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        self.within(format!("fn {}", item.sig.ident), |visitor| {
+            visit::visit_item_fn(visitor, item)
+        });
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast syn::ImplItemFn) {
+        self.within(format!("fn {}", item.sig.ident), |visitor| {
+            visit::visit_impl_item_fn(visitor, item)
+        });
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast syn::TraitItemFn) {
+        self.within(format!("fn {}", item.sig.ident), |visitor| {
+            visit::visit_trait_item_fn(visitor, item)
+        });
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        let is_block = mac
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "bestbefore_block");
+        if is_block {
+            match mac.parse_body::<BlockArgs>() {
+                Ok(BlockArgs { args, body }) => {
+                    self.add(mac.span(), "code block".to_string(), None, Ok(args));
+                    self.visit_expr(&body);
+                }
+                Err(err) => self.add(mac.span(), "code block".to_string(), None, Err(err)),
+            }
+        }
+        visit::visit_macro(self, mac);
+    }
+}
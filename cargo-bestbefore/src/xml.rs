@@ -0,0 +1,32 @@
+//! Escaping text for XML and HTML output.
+
+/// Escapes `text` for use in element content and quoted HTML attribute values.
+///
+/// Control characters that XML 1.0 does not allow are dropped.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            '\n' | '\r' | '\t' => escaped.push(c),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escapes `text` for use in a quoted XML attribute value.
+///
+/// Tabs and line breaks are written as character references, which parsers would otherwise
+/// normalize to spaces.
+pub(crate) fn escape_attribute(text: &str) -> String {
+    escape(text)
+        .replace('\t', "&#9;")
+        .replace('\n', "&#10;")
+        .replace('\r', "&#13;")
+}
@@ -0,0 +1,158 @@
+//! Tests of `bump` on a copy of the `billing` fixture: the diff it prints, the sources and
+//! configuration it writes, and a second bump of the same annotations.
+//!
+//! The results are compared with the files in `tests/golden/bump`. Set `BESTBEFORE_BLESS=1` to
+//! rewrite them after an intended change of the output.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use bestbefore_core::environment::VARS;
+
+/// The files of the fixture that a bump may change.
+const FILES: [&str; 3] = ["Cargo.toml", "src/lib.rs", "src/legacy.rs"];
+
+fn tests_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+/// A fresh copy of the `billing` fixture, in a directory of its own for the test `name`.
+fn fixture(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    let source = tests_dir().join("fixtures").join("billing");
+    for file in FILES {
+        let target = dir.join(file);
+        fs::create_dir_all(target.parent().unwrap()).unwrap();
+        fs::copy(source.join(file), &target).unwrap();
+    }
+    dir
+}
+
+fn run(dir: &Path, args: &[&str]) -> Output {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_cargo-bestbefore"));
+    command
+        .args(["bestbefore", "bump", "--at", "2025-07"])
+        .args(args)
+        .arg("--manifest-path")
+        .arg(dir.join("Cargo.toml"));
+    for var in VARS {
+        command.env_remove(var);
+    }
+    command.output().expect("failed to run cargo-bestbefore")
+}
+
+/// Runs `bump` with `args` on the fixture copy in `dir`, which has to succeed.
+fn bump(dir: &Path, args: &[&str]) -> Output {
+    let output = run(dir, args);
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "bump failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+/// Compares `actual` with the golden file `name` in `tests/golden/bump`.
+fn golden(name: &str, actual: &str) {
+    let golden = tests_dir().join("golden").join("bump").join(name);
+    if env::var_os("BESTBEFORE_BLESS").is_some() {
+        fs::create_dir_all(golden.parent().unwrap()).unwrap();
+        fs::write(&golden, actual).expect("failed to write the golden file");
+    }
+    let expected = fs::read_to_string(&golden)
+        .unwrap_or_else(|e| panic!("failed to read {}: {}", golden.display(), e));
+    assert_eq!(
+        actual,
+        expected,
+        "output differs from {}, rerun with BESTBEFORE_BLESS=1 if intended",
+        golden.display()
+    );
+}
+
+/// Compares the files of the fixture copy in `dir` with the golden files below `stage`.
+fn golden_files(dir: &Path, stage: &str) {
+    for file in FILES {
+        let actual = fs::read_to_string(dir.join(file)).unwrap();
+        golden(&format!("{}/{}", stage, file), &actual);
+    }
+}
+
+const RECORDED: [&str; 4] = ["--reason", "Waiting for the ledger", "--approver", "carol"];
+
+#[test]
+fn prints_a_diff_without_writing() {
+    let dir = fixture("bump-dry-run");
+    let output = bump(
+        &dir,
+        &[&["--due", "2025-06", "--to", "2025-10"], &RECORDED[..]].concat(),
+    );
+    golden("dry-run.diff", &String::from_utf8(output.stdout).unwrap());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Would move 3 annotations in 3 files; run again with --write to apply\n"
+    );
+    let fixture = tests_dir().join("fixtures").join("billing");
+    for file in FILES {
+        assert_eq!(
+            fs::read_to_string(dir.join(file)).unwrap(),
+            fs::read_to_string(fixture.join(file)).unwrap(),
+            "{} was written",
+            file
+        );
+    }
+}
+
+#[test]
+fn writes_the_sources_and_the_snooze_table() {
+    let dir = fixture("bump-write");
+    let output = bump(
+        &dir,
+        &[
+            &["--due", "2025-06", "--to", "2025-10", "--write"],
+            &RECORDED[..],
+        ]
+        .concat(),
+    );
+    assert!(output.stdout.is_empty());
+    golden_files(&dir, "first");
+
+    // The entries are extended rather than added again.
+    bump(
+        &dir,
+        &[
+            &["--due", "2025-10", "--to", "2025-11", "--write"],
+            &RECORDED[..],
+        ]
+        .concat(),
+    );
+    golden_files(&dir, "second");
+}
+
+#[test]
+fn refuses_to_extend_beyond_the_policy() {
+    let dir = fixture("bump-policy");
+    // `legacy-rounding` has been extended once, and the policy allows two.
+    let args = ["--id", "legacy-rounding", "--write"];
+    bump(
+        &dir,
+        &[&args[..], &["--to", "2025-10"], &RECORDED[..]].concat(),
+    );
+    let before = fs::read_to_string(dir.join("Cargo.toml")).unwrap();
+
+    let output = run(
+        &dir,
+        &[&args[..], &["--to", "2025-11"], &RECORDED[..]].concat(),
+    );
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("has been extended 3 times, but the policy allows 2"),
+        "{}",
+        stderr
+    );
+    assert_eq!(fs::read_to_string(dir.join("Cargo.toml")).unwrap(), before);
+}
@@ -0,0 +1,13 @@
+use bestbefore::bestbefore;
+
+#[bestbefore("06.2025", message = "Use the ledger instead")]
+pub fn new() {}
+
+/// Its message and owner need escaping in every format.
+#[bestbefore(
+    "2025-04",
+    expires = "2025-06",
+    owner = "R&D \"rates\" <ops>",
+    message = "Replace <Rate> & \"tiers\", see C:\\rates; a|b\tc €"
+)]
+pub fn rates() {}
@@ -0,0 +1,46 @@
+use bestbefore::{bestbefore, bestbefore_block};
+
+mod legacy;
+
+/// Fees as charged before 2024.
+#[bestbefore("03.2025", expires = "2099-Q4", owner = "team-payments", ticket = "PAY-1234")]
+pub fn fee(amount: u64) -> u64 {
+    amount / 100
+}
+
+#[bestbefore(expires = "2025-05", id = "legacy-rounding", replacement = crate::round)]
+pub fn round_legacy(amount: u64) -> u64 {
+    amount
+}
+
+pub fn round(amount: u64) -> u64 {
+    amount
+}
+
+#[bestbefore(stages(note = "2025-01", warn = "2099-01"))]
+pub struct Invoice {
+    #[bestbefore(expires = "2025-06-15")]
+    pub currency: String,
+}
+
+pub struct Ledger;
+
+impl Ledger {
+    #[bestbefore("2025-W10")]
+    pub fn new() -> Self {
+        Ledger
+    }
+
+    pub fn total(&self) -> u64 {
+        let discount = bestbefore_block!("2099-Q1", 1);
+        let surcharge = bestbefore_block!(expires = "2025-Q1", 2);
+        discount + surcharge
+    }
+}
+
+pub mod export {
+    use bestbefore::bestbefore;
+
+    #[bestbefore(stages(warn = "2025-01", deny_in_ci = "2025-02", error = "2099-01"))]
+    pub fn new() {}
+}
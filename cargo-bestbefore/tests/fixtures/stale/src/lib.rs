@@ -0,0 +1,6 @@
+pub mod fees {
+    use bestbefore::bestbefore;
+
+    #[bestbefore("2099-01", expires = "2099-06")]
+    pub fn fee() {}
+}
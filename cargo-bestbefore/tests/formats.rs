@@ -0,0 +1,296 @@
+//! Golden tests of the output formats: each fixture crate under `tests/fixtures` is checked or
+//! reported on at a fixed date and the output compared with
+//! `tests/golden/<fixture>.<format>.<extension>`.
+//!
+//! Set `BESTBEFORE_BLESS=1` to rewrite the golden files after an intended change of the output.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use bestbefore_core::environment::VARS;
+
+const AT: &str = "2025-07";
+
+fn tests_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+/// Runs `cargo bestbefore` with `args` on `fixture`, at [`AT`] and with `env` as the only
+/// environment variables of the crate, and returns its output and exit code.
+///
+/// `fixture` is the name of a fixture, or the absolute path of a crate.
+fn cargo_bestbefore(
+    fixture: impl AsRef<Path>,
+    args: &[&str],
+    env: &[(&str, &str)],
+) -> (String, Option<i32>) {
+    let manifest = tests_dir()
+        .join("fixtures")
+        .join(fixture)
+        .join("Cargo.toml");
+    let mut command = Command::new(env!("CARGO_BIN_EXE_cargo-bestbefore"));
+    command
+        .arg("bestbefore")
+        .args(args)
+        .args(["--at", AT, "--manifest-path"])
+        .arg(&manifest);
+    for var in VARS {
+        command.env_remove(var);
+    }
+    command.envs(env.iter().copied());
+    let output = command.output().expect("failed to run cargo-bestbefore");
+    assert!(
+        output.stderr.is_empty(),
+        "unexpected diagnostics: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).expect("output is not UTF-8");
+    (stdout, output.status.code())
+}
+
+/// Compares `actual` with the golden file `name`.
+fn golden(name: &str, actual: &str) {
+    let golden = tests_dir().join("golden").join(name);
+    if env::var_os("BESTBEFORE_BLESS").is_some() {
+        fs::write(&golden, actual).expect("failed to write the golden file");
+    }
+    let expected = fs::read_to_string(&golden)
+        .unwrap_or_else(|e| panic!("failed to read {}: {}", golden.display(), e));
+    assert_eq!(
+        actual,
+        expected,
+        "output differs from {}, rerun with BESTBEFORE_BLESS=1 if intended",
+        golden.display()
+    );
+}
+
+fn check(fixture: &str, format: &str, extension: &str) -> (String, Option<i32>) {
+    let (actual, code) = cargo_bestbefore(fixture, &["check", "--format", format], &[]);
+    golden(&format!("{}.{}.{}", fixture, format, extension), &actual);
+    (actual, code)
+}
+
+#[test]
+fn github_annotations() {
+    let (_, code) = check("billing", "github", "txt");
+    assert_eq!(code, Some(2));
+}
+
+#[test]
+fn gitlab_code_quality() {
+    let (_, code) = check("billing", "gitlab", "json");
+    assert_eq!(code, Some(2));
+}
+
+#[test]
+fn fingerprints_are_distinct() {
+    let (json, _) = check("billing", "gitlab", "json");
+    let fingerprints: Vec<&str> = json
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("\"fingerprint\": \""))
+        .collect();
+    assert_eq!(fingerprints.len(), 8);
+    let mut unique = fingerprints.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(
+        unique.len(),
+        fingerprints.len(),
+        "fingerprints collide: {:?}",
+        fingerprints
+    );
+}
+
+#[test]
+fn nothing_due() {
+    let (github, code) = check("clean", "github", "txt");
+    assert_eq!((github.as_str(), code), ("", Some(0)));
+    let (gitlab, code) = check("clean", "gitlab", "json");
+    assert_eq!((gitlab.trim(), code), ("[]", Some(0)));
+}
+
+#[test]
+fn snoozes_that_match_nothing_are_reported() {
+    let manifest = tests_dir()
+        .join("fixtures")
+        .join("stale")
+        .join("Cargo.toml");
+    let mut command = Command::new(env!("CARGO_BIN_EXE_cargo-bestbefore"));
+    command
+        .args(["bestbefore", "check", "--at", AT, "--manifest-path"])
+        .arg(&manifest);
+    for var in VARS {
+        command.env_remove(var);
+    }
+    let output = command.output().expect("failed to run cargo-bestbefore");
+    let stderr = String::from_utf8(output.stderr).expect("diagnostics are not UTF-8");
+    assert_eq!(
+        stderr.trim_end(),
+        format!(
+            "warning: The snooze of item 'stale::fee' in {} matches no annotation",
+            manifest.display()
+        )
+    );
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn sarif_log() {
+    let (sarif, code) = check("billing", "sarif", "json");
+    assert_eq!(code, Some(2));
+    // Quotes, backslashes and control characters are escaped, the rest is kept.
+    assert!(sarif.contains(r#""text": "Replace <Rate> & \"tiers\", see C:\\rates; a|b\tc €""#));
+    let fingerprints: Vec<&str> = sarif
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("\"bestbefore/v1\": \""))
+        .collect();
+    assert_eq!(fingerprints.len(), 8);
+    let mut unique = fingerprints.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(unique.len(), fingerprints.len());
+}
+
+#[test]
+fn sarif_levels_follow_the_mode() {
+    // The rules stay those of the stages, while the levels of the results follow the mode.
+    let (sarif, code) = cargo_bestbefore(
+        "billing",
+        &["check", "--format", "sarif"],
+        &[("BESTBEFORE_MODE", "strict")],
+    );
+    golden("billing.strict.sarif.json", &sarif);
+    assert_eq!(code, Some(2));
+    let (sarif, code) = cargo_bestbefore(
+        "billing",
+        &["check", "--format", "sarif"],
+        &[("BESTBEFORE_MODE", "warn-only")],
+    );
+    golden("billing.warn-only.sarif.json", &sarif);
+    assert_eq!(code, Some(1));
+}
+
+#[test]
+fn junit_report() {
+    let (junit, code) = check("billing", "junit", "xml");
+    assert_eq!(code, Some(2));
+    // A suite per owner, in alphabetical order, and the annotations without one last.
+    let suites: Vec<&str> = junit
+        .lines()
+        .filter(|line| line.trim_start().starts_with("<testsuite "))
+        .collect();
+    assert_eq!(
+        suites,
+        [
+            "  <testsuite name=\"R&amp;D &quot;rates&quot; &lt;ops&gt;\" tests=\"1\" failures=\"1\" errors=\"0\" skipped=\"0\">",
+            "  <testsuite name=\"team-payments\" tests=\"1\" failures=\"0\" errors=\"0\" skipped=\"1\">",
+            "  <testsuite name=\"unassigned\" tests=\"8\" failures=\"2\" errors=\"0\" skipped=\"4\">",
+        ]
+    );
+    assert!(junit.contains(
+        "<testsuites name=\"bestbefore\" tests=\"10\" failures=\"3\" errors=\"0\" skipped=\"5\">"
+    ));
+    // Tabs are kept in attributes, where a parser would turn them into spaces.
+    assert!(junit.contains(
+        "<failure message=\"Replace &lt;Rate&gt; &amp; &quot;tiers&quot;, see C:\\rates; a|b&#9;c €\" \
+         type=\"expired\">Replace &lt;Rate&gt; &amp; &quot;tiers&quot;, see C:\\rates; a|b\tc €\n"
+    ));
+}
+
+#[test]
+fn debt_report() {
+    let (markdown, code) = cargo_bestbefore("billing", &["debt"], &[]);
+    golden("billing.markdown.md", &markdown);
+    assert_eq!(code, Some(0));
+    assert!(markdown.contains("| **R&amp;D \"rates\" &lt;ops&gt;** | 1 | 1 | 0 | 2025-06-30 |"));
+
+    let (html, code) = cargo_bestbefore("billing", &["debt", "--format", "html"], &[]);
+    golden("billing.html.html", &html);
+    assert_eq!(code, Some(0));
+    assert!(html.contains("<td>R&amp;D &quot;rates&quot; &lt;ops&gt;</td>"));
+}
+
+#[test]
+fn burndown_stays_near_today() {
+    // The fixture has deadlines up to 2099, which the chart leaves to the table.
+    let (markdown, _) = cargo_bestbefore("billing", &["debt"], &[]);
+    let svg = markdown
+        .lines()
+        .find(|line| line.starts_with("<svg"))
+        .expect("no chart");
+    assert!(svg.contains(">2025-01</text>"), "{}", svg);
+    assert!(svg.contains(">2027-07</text>"), "{}", svg);
+    assert!(!svg.contains(">2028-"), "{}", svg);
+    assert!(markdown.contains("| 2099-12 |"));
+}
+
+#[test]
+fn calendar() {
+    let (ics, code) = cargo_bestbefore("billing", &["calendar"], &[]);
+    golden("billing.calendar.ics", &ics);
+    assert_eq!(code, Some(0));
+    assert!(ics
+        .split_terminator('\n')
+        .all(|line| line.len() <= 76 && line.ends_with('\r')));
+    // Unfolded, text values escape backslashes, semicolons and commas.
+    let unfolded = ics.replace("\r\n ", "");
+    assert!(unfolded.contains(
+        "DESCRIPTION:billing::legacy::rates\\nReplace <Rate> & \"tiers\"\\, see C:\\\\rates\\; a|b\tc €"
+    ));
+
+    let (owned, _) = cargo_bestbefore(
+        "billing",
+        &[
+            "calendar",
+            "--owner",
+            "team-payments",
+            "--owner",
+            "R&D \"rates\" <ops>",
+        ],
+        &[],
+    );
+    golden("billing.owners.calendar.ics", &owned);
+    let unfolded = owned.replace("\r\n ", "");
+    assert!(unfolded.contains("X-WR-CALNAME:bestbefore: team-payments\\, R&D \"rates\" <ops>\r\n"));
+    let summaries: Vec<&str> = unfolded
+        .lines()
+        .filter(|line| line.starts_with("SUMMARY:"))
+        .collect();
+    assert_eq!(
+        summaries,
+        [
+            "SUMMARY:Warns: billing::fee",
+            "SUMMARY:Warns: billing::legacy::rates",
+            "SUMMARY:Expires: billing::legacy::rates",
+            "SUMMARY:Expires: billing::fee",
+        ]
+    );
+}
+
+#[test]
+fn calendar_events_keep_their_uid_when_code_moves() {
+    let uids = |ics: &str| -> Vec<String> {
+        ics.lines()
+            .filter(|line| line.starts_with("UID:"))
+            .map(str::to_string)
+            .collect()
+    };
+    let (before, _) = cargo_bestbefore("billing", &["calendar"], &[]);
+
+    // The same crate, with lines added above the annotations.
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("calendar-moved");
+    let fixture = tests_dir().join("fixtures").join("billing");
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::copy(fixture.join("Cargo.toml"), dir.join("Cargo.toml")).unwrap();
+    for file in ["src/lib.rs", "src/legacy.rs"] {
+        let source = fs::read_to_string(fixture.join(file)).unwrap();
+        fs::write(dir.join(file), format!("//! Moved.\n\n{}", source)).unwrap();
+    }
+    let (after, _) = cargo_bestbefore(&dir, &["calendar"], &[]);
+
+    assert_eq!(uids(&before).len(), 14);
+    assert_eq!(uids(&before), uids(&after));
+    assert_ne!(before, after);
+}
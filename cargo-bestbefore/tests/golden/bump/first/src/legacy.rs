@@ -0,0 +1,13 @@
+use bestbefore::bestbefore;
+
+#[bestbefore("10.2025", message = "Use the ledger instead")]
+pub fn new() {}
+
+/// Its message and owner need escaping in every format.
+#[bestbefore(
+    "2025-08",
+    expires = "2025-10",
+    owner = "R&D \"rates\" <ops>",
+    message = "Replace <Rate> & \"tiers\", see C:\\rates; a|b\tc €"
+)]
+pub fn rates() {}
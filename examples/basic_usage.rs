@@ -19,6 +19,22 @@ fn deprecated_with_message() {
     println!("This function has a custom warning message");
 }
 
+// The replacement is a real path: the warning suggests it, and renaming new_api breaks the build
+#[bestbefore("02.2023", replacement = crate::new_api)]
+fn deprecated_with_replacement() {
+    println!("This function suggests new_api() in its warning");
+}
+
+fn new_api() {
+    println!("This is the replacement");
+}
+
+// Dates can also be exact days, ISO weeks or quarters
+#[bestbefore("2025-W10", expires = "2030-Q2")]
+fn weekly_deadline() {
+    println!("This function warns from ISO week 11 of 2025 and fails to compile from July 2030");
+}
+
 // Example of applying to non-function items
 #[bestbefore("06.2023")]
 mod legacy_module {
@@ -36,6 +52,9 @@ fn main() {
     future_warning();
     expired_function();
     deprecated_with_message();
+    deprecated_with_replacement();
+    new_api();
+    weekly_deadline();
     legacy_module::old_function();
 
     let old = OldStructure {
@@ -29,8 +29,8 @@
  * }
  *
  * // Generate a warning if compiled after January 2023
- * // Cause a compilation error if compiled after December 2023
- * #[bestbefore("01.2023", expires = "12.2023")]
+ * // Cause a compilation error if compiled after December 2099
+ * #[bestbefore("01.2023", expires = "12.2099")]
  * fn very_old_function() {
  *     // ...
  * }
@@ -46,37 +46,199 @@
  * fn deprecated_with_message() {
  *     // ...
  * }
+ *
+ * // Escalate step by step: documentation note, warning, failing CI builds, failing all builds
+ * #[bestbefore(stages(note = "2025-01", warn = "2025-04", deny_in_ci = "2099-06", error = "2099-09"))]
+ * fn escalating_function() {
+ *     // ...
+ * }
  * ```
  *
  * ## Date format
  *
- * The macro uses the "MM.YYYY" format for simplicity, for example:
- * - "03.2024" represents March 2024
- * - "12.2023" represents December 2023
+ * Dates can be given with different granularities:
+ * - "03.2024" or "2024-03" represents March 2024
+ * - "2024-03-15" represents the 15th of March 2024
+ * - "2024-W11" represents ISO week 11 of 2024
+ * - "Q1.2024" or "2024-Q1" represents the first quarter of 2024
+ *
+ * Every date denotes a whole period. A threshold is reached once that period is over,
+ * so "03.2024" triggers from the 1st of April 2024 and "2024-Q1" from the 1st of April 2024.
+ *
+ * ## Clock
+ *
+ * The current day is resolved from, in order of precedence:
+ *
+ * 1. the `BESTBEFORE_DATE` environment variable, which is useful for testing. The value accepts
+ *    any of the formats above and is taken to be the first day of the given period.
+ * 2. the `SOURCE_DATE_EPOCH` environment variable (seconds since the Unix epoch), as set by
+ *    reproducible-build pipelines.
+ * 3. the system clock.
+ *
+ * The latter two are converted to a day in UTC, so the result does not depend on the build
+ * machine's time zone. Set `BESTBEFORE_TZ` to `local` or to a fixed offset such as `+02:00` to
+ * use a different time zone. Every warning and error reports the clock that was used.
+ *
+ * ## Configuration
+ *
+ * Project-wide defaults are read from a `bestbefore.toml` file, or from the
+ * `[package.metadata.bestbefore]` or `[workspace.metadata.bestbefore]` table of a `Cargo.toml`.
+ * The first of these found from the crate's directory upward (up to the workspace root, or the
+ * package itself outside of a workspace) is used:
+ *
+ * ```toml
+ * # Enforcement mode, see below
+ * mode = "normal"
+ * # Annotations without an expiration date expire this long after their last date
+ * grace_period = "3 months"
+ * # Parameters every annotation has to give: owner, ticket, replacement, message or expires
+ * required = ["owner"]
+ * # See the `ticket` parameter
+ * ticket_url = "https://tracker.local/browse/{ticket}"
+ * ticket_pattern = "[A-Z]+-[0-9]+"
+ * # Append a record of every annotation to this file, see below
+ * report = "target/bestbefore.jsonl"
+ *
+ * # Templates replacing the default messages of each stage, unless an annotation gives its own.
+ * # Available placeholders are {item}, {date}, {owner} and {ticket}.
+ * [messages]
+ * warn = "{item} is past its best-before date ({date}), ask {owner}"
+ * error = "{item} has expired ({date}), see {ticket}"
+ * ```
+ *
+ * An invalid configuration fails the compilation of every annotation.
+ *
+ * ## Modes
+ *
+ * The enforcement mode applies to all annotations of a build. It is taken from the
+ * `BESTBEFORE_MODE` environment variable, or else from `mode` in the configuration:
+ *
+ * - `off`: no warnings or errors at all
+ * - `warn-only`: expired code produces a warning, reported at the code itself as well as where
+ *   it is used, instead of an error. Useful for release branches and hotfixes.
+ * - `normal` (default): warnings and errors as configured by the annotations
+ * - `strict`: warnings are errors, except for snoozed code
+ *
+ * Every warning and error states the mode in effect.
+ *
+ * ## Snoozing
+ *
+ * When a deadline has to slip, the expiry can be postponed in the configuration instead of
+ * editing the date in the source. Each snooze is recorded with its reason and approver, and is
+ * bounded by how many times and how far it may be extended:
  *
- * The day is assumed to be the first of the month.
+ * ```toml
+ * [[snooze]]
+ * id = "legacy-fees"              # the annotation's `id`, or
+ * # item = "billing::legacy::fee" # the annotated item's path
+ * until = "2026-03"
+ * reason = "Blocked on the billing migration"
+ * approver = "alice"
+ * extensions = 1                  # how many times the deadline was postponed so far
+ * max_extensions = 2
+ * max_until = "2026-06"           # the latest `until` may ever be moved to
+ * ```
+ *
+ * Until `until` has passed, expired code warns instead of failing the build, at the code itself
+ * and at its uses. Diagnostics of snoozed annotations mention the snooze. A snooze beyond its
+ * limits is a configuration error.
+ *
+ * Item paths start with the crate name, followed by the modules of the source file, the modules
+ * declared inline around the item and the item's name, such as `billing::legacy::fee` for
+ * `fn fee` in `src/legacy.rs` of crate `billing`. Fields and variants are named like
+ * `Config::timeout` and `Mode::Legacy`.
+ *
+ * ## Report
+ *
+ * With `report` in the configuration, or the `BESTBEFORE_REPORT` environment variable, every
+ * expansion appends one JSON object per annotation to the given file (JSON Lines). A relative
+ * path is taken relative to the configuration file, or for the environment variable to the
+ * workspace root. The file is locked for each record, so parallel compilation is safe.
  *
- * ## Environment variable
+ * Each record has these fields, with `null` for missing values:
  *
- * You can override the current date by setting the `BESTBEFORE_DATE` environment variable,
- * which is useful for testing. The value should be in the same "MM.YYYY" format.
+ * | Field | Content |
+ * |-------|---------|
+ * | `schema` | `"bestbefore-report/1"`; the number changes with incompatible changes |
+ * | `crate` | the crate being compiled |
+ * | `item` | the annotated code as named in diagnostics, such as `"struct Config"` |
+ * | `path` | the item path as used for snoozing, such as `"billing::legacy::fee"` |
+ * | `id` | the annotation's `id` |
+ * | `file`, `line`, `column` | where the annotation is, relative to the package root |
+ * | `dates` | the stages, each as `{"stage": "warn", "date": "06.2025", "last_day": "2025-06-30"}` |
+ * | `state` | the outcome: `"fresh"`, `"note"`, `"warning"` or `"error"` |
+ * | `stage` | the stage whose date has passed: `"note"`, `"warn"`, `"deny_in_ci"` or `"error"` |
+ * | `message` | the text of the note, warning or error |
+ * | `owner`, `ticket`, `replacement` | as given, with the ticket as a link if configured |
+ * | `snoozed_until` | the date of an active snooze |
+ * | `mode` | the enforcement mode |
+ * | `today` | the day the annotation was evaluated against, as `YYYY-MM-DD` |
+ *
+ * Records are only written when a crate is actually compiled, and a crate compiled again adds
+ * its records again. For a complete inventory, remove the file and build from a clean state,
+ * and deduplicate by `file` and `line` when aggregating.
  */
 
-use chrono::{Datelike, Local, NaiveDate};
+#![cfg_attr(feature = "nightly", feature(proc_macro_tracked_env))]
+
+mod tracking;
+
+use bestbefore_core::args::{BestBeforeArgs, BlockArgs};
+use bestbefore_core::context::{Context, Outcome, Subject};
+use bestbefore_core::eval::Verdict;
+use bestbefore_core::location::{self, Location};
+use bestbefore_core::report;
+use bestbefore_core::target::Target;
 use proc_macro::TokenStream;
 use proc_macro2::{Span, TokenStream as TokenStream2};
-use quote::{quote, ToTokens};
+use quote::{quote, quote_spanned, ToTokens};
 use std::env;
-use syn::{parse::Parse, parse::ParseStream, parse_macro_input, LitStr, Token};
+use std::path::{Path, PathBuf};
+use syn::{parse_macro_input, spanned::Spanned};
 
 /// A procedural macro that generates warnings or errors at compile time
 /// when the compile date exceeds the specified expiration date.
 ///
 /// # Arguments
 ///
-/// * First positional argument: Optional date string in "MM.YYYY" format for warning threshold
-/// * `expires`: Optional date string in "MM.YYYY" format for error threshold
+/// * First positional argument: Optional date string for warning threshold
+/// * `expires`: Optional date string for error threshold
+///
+/// Dates are accepted as "MM.YYYY", "YYYY-MM-DD", "YYYY-MM", "YYYY-Www", "Qn.YYYY" or "YYYY-Qn".
 /// * `message`: Optional custom message for warnings/errors
+/// * `warn_message`: Optional custom message for the warning only
+/// * `expire_message`: Optional custom message for the expiration error only
+///
+/// The two specific messages can also be written as `message(warn = "...", expire = "...")`.
+/// * `warn_at`: Where warnings are reported: `"use"` (default) wherever the item is used,
+///   `"definition"` at the annotated item itself, or `"both"`. Reporting at the definition also
+///   covers items without callers and modules that are only glob-imported.
+/// * `owner`: Optional team or person responsible for the code. It is named in every warning
+///   and error, so expired code always says who is accountable.
+/// * `ticket`: Optional issue tracker reference, such as `"PAY-1234"`. Projects can configure a
+///   URL template with `ticket_url` (for example `https://tracker.local/browse/{ticket}`) to turn
+///   it into a link in every warning and error, and a regular expression with `ticket_pattern`
+///   (for example `[A-Z]+-[0-9]+`) that every ticket has to match. Both are set in the project
+///   configuration and can be overridden with the `BESTBEFORE_TICKET_URL` and
+///   `BESTBEFORE_TICKET_PATTERN` environment variables.
+/// * `id`: Optional stable identifier of the annotation, used to refer to it from the
+///   configuration, such as in a snooze entry.
+/// * `replacement`: Optional path to the code to use instead, such as `crate::new_api`. It is not
+///   a string: the expansion refers to it, so the build fails as soon as the replacement is renamed
+///   or removed, and warnings suggest it as "use `crate::new_api` instead". Of associated items
+///   and enum variants, such as `crate::Client::connect`, only the type is checked.
+///
+/// # Stages
+///
+/// Instead of (or in addition to) the warning and expiration dates, an escalation ladder can be
+/// given with `stages(note = "...", warn = "...", deny_in_ci = "...", error = "...")`. Any subset
+/// of the stages may be used, listed in this order. Once a stage's date has passed:
+///
+/// * `note`: the item's documentation gets a "Best before" note, no diagnostic is emitted
+/// * `warn`: uses of the item produce a deprecation warning (same as the positional date)
+/// * `deny_in_ci`: a deprecation warning locally, a compile error when the `CI` environment
+///   variable is set (as done by GitHub Actions, GitLab CI and most other CI systems)
+/// * `error`: a compile error (same as `expires`)
 ///
 /// # Examples
 ///
@@ -90,12 +252,18 @@ use syn::{parse::Parse, parse::ParseStream, parse_macro_input, LitStr, Token};
 /// }
 ///
 /// // Generate a warning if compiled after January 2023
-/// // Cause a compilation error if compiled after December 2023
-/// #[bestbefore("01.2023", expires="12.2023")]
+/// // Cause a compilation error if compiled after December 2099
+/// #[bestbefore("01.2023", expires="12.2099")]
 /// fn very_old_function() {
 ///     // This will:
-///     // - Generate a warning if compiled after March 2023
-///     // - Cause a compilation error if compiled after December 2023
+///     // - Generate a warning if compiled after January 2023
+///     // - Cause a compilation error if compiled after December 2099
+/// }
+///
+/// // Dates can also be given as days, ISO weeks or quarters
+/// #[bestbefore("2025-06-15", expires = "2099-Q3")]
+/// fn day_and_quarter() {
+///     // Warns from the 16th of June 2025, fails to compile from October 2099
 /// }
 ///
 /// // Only specify expiration date without a warning date
@@ -110,12 +278,40 @@ use syn::{parse::Parse, parse::ParseStream, parse_macro_input, LitStr, Token};
 /// fn deprecated_with_message() {
 ///     // This will generate a warning with custom message if compiled after March 2024
 /// }
+///
+/// // Point to the replacement, which the compiler checks for existence
+/// #[bestbefore("02.2023", replacement = new_api)]
+/// fn deprecated_with_replacement() {}
+///
+/// fn new_api() {}
+///
+/// // Use different messages for the warning and the expiration error
+/// #[bestbefore(
+///     "02.2023",
+///     expires = "12.2099",
+///     message(warn = "Migrate to new_api() soon", expire = "This is now blocking, delete it")
+/// )]
+/// fn staged_messages() {}
+///
+/// // Warn at the function itself, even if nothing calls it
+/// #[bestbefore("02.2023", warn_at = "definition")]
+/// fn unused_legacy_function() {}
+///
+/// // Name who is responsible for removing the code
+/// #[bestbefore("02.2023", owner = "team-payments", ticket = "PAY-1234")]
+/// fn owned_legacy_function() {}
+///
+/// // Escalate step by step
+/// #[bestbefore(stages(note = "2025-01", warn = "2025-04", deny_in_ci = "2099-06", error = "2099-09"))]
+/// fn escalating() {}
 /// ```
 ///
 /// # Validation
 ///
-/// The macro validates that if an expiration date is provided, it must be after the warning date.
+/// The macro validates that if an expiration date is provided, it is not before the warning date.
 /// This ensures a logical progression from "should be updated" (warning) to "must be removed" (error).
+/// In general, the dates of all configured stages must increase from `note` to `error`; only a
+/// warning date and an expiration date may be the same, in which case the code expires outright.
 ///
 /// # Application Target
 ///
@@ -126,182 +322,650 @@ use syn::{parse::Parse, parse::ParseStream, parse_macro_input, LitStr, Token};
 /// - Traits
 /// - Implementations
 /// - Enums
+/// - Methods, associated consts and associated types in impl blocks and traits
+/// - Struct and union fields and enum variants
+///
+/// Rust does not allow attribute macros on fields and variants. Annotations on them are evaluated
+/// by a `#[bestbefore]` on the enclosing struct, union or enum, which may be given without
+/// arguments to only evaluate its members:
+///
+/// ```rust
+/// use bestbefore::bestbefore;
+///
+/// #[bestbefore]
+/// pub struct Config {
+///     #[bestbefore("2099-06", message = "Use timeout_ms instead")]
+///     pub timeout: u32,
+///     pub timeout_ms: u64,
+/// }
+///
+/// pub struct Client;
+///
+/// impl Client {
+///     #[bestbefore("2099-06", message = "Use connect_with() instead")]
+///     pub fn connect(&self) {}
+/// }
+/// ```
+///
+/// On an inherent impl block, the warning is attached to every method, associated const and
+/// associated type inside the block. Trait impls cannot be deprecated in Rust, so for them the
+/// warning is always reported at the impl block itself (see `warn_at`). The same goes for the
+/// items of trait impls, so annotated methods also warn at their definition, as the macro cannot
+/// see which impl they are in. It recognizes methods by `self` or `Self` in their signature;
+/// annotate other associated functions of trait impls with `warn_at = "definition"`.
 #[proc_macro_attribute]
 pub fn bestbefore(attr: TokenStream, item: TokenStream) -> TokenStream {
-    fn compile_error(message: String) -> TokenStream {
-        let message = syn::LitStr::new(&message, Span::call_site());
-        quote! {
-            compile_error!(#message);
+    let mut target = parse_macro_input!(item as Target);
 
+    let context = match Context::from_env() {
+        Ok(context) => context,
+        Err(message) => {
+            return syn::Error::new(Span::call_site(), message)
+                .to_compile_error()
+                .into()
         }
-        .into()
-    }
+    };
 
-    fn format_date(date: NaiveDate) -> String {
-        format!("{:02}.{:02}", date.month(), date.year())
-    }
+    // Without arguments on a struct or enum, only the annotations of its fields and variants
+    // are evaluated.
+    let attr_args = if attr.is_empty() && target.has_members() {
+        None
+    } else {
+        Some(parse_macro_input!(attr as BestBeforeArgs))
+    };
 
-    let attr_args = parse_macro_input!(attr as BestBeforeArgs);
-    let input = parse_macro_input!(item as syn::Item);
-
-    let current_date = env::var("BESTBEFORE_DATE")
-        .as_deref()
-        .map(parse_date)
-        .unwrap_or_else(|_| {
-            let now = Local::now();
-            NaiveDate::from_ymd_opt(now.year(), now.month(), 1).unwrap()
-        });
-
-    let item_name = item_name(&input);
-
-    if let Some(expires_date) = attr_args.expires_date {
-        if expires_date != attr_args.warning_date && expires_date <= attr_args.warning_date {
-            return compile_error(format!(
-                "Invalid date: expiration date ({}) must be after warning date ({})",
-                format_date(expires_date),
-                format_date(attr_args.warning_date)
-            ));
-        }
+    let mut hidden = Hidden::default();
 
-        if current_date > expires_date {
-            let message = attr_args.message.unwrap_or_else(|| {
-                format!(
-                    "Code '{}' has expired (after {}): consider removing this code",
-                    item_name,
-                    format_date(expires_date)
-                )
-            });
-            return compile_error(message);
+    if let Some(mut attr_args) = attr_args {
+        let subject = subject(target.name(), target.path_name(), Span::call_site());
+        if let Err(err) = apply(&mut target, &mut attr_args, &subject, &context, &mut hidden) {
+            return err.to_compile_error().into();
         }
     }
 
-    let mut result = TokenStream2::new();
+    let mut errors = TokenStream2::new();
+    if let Err(err) = expand_members(&mut target, &context, &mut hidden) {
+        errors.extend(err.to_compile_error());
+    }
 
-    if current_date > attr_args.warning_date {
-        let message = attr_args.message.unwrap_or_else(|| {
+    let mut trailer = TokenStream2::new();
+    if !target.place_hidden(hidden.warnings, &mut trailer) {
+        return syn::Error::new(
+            Span::call_site(),
             format!(
-                "Code '{}' past warning date ({}): consider updating or removing this code",
-                item_name,
-                format_date(attr_args.warning_date)
-            )
-        });
-
-        let warning = quote! {
-            #[warn(deprecated)]
-            #[deprecated(note = #message)]
-        };
-
-        result.extend(warning);
+                "warn_at = \"definition\" is not supported on {}, use warn_at = \"use\" instead",
+                target.name()
+            ),
+        )
+        .to_compile_error()
+        .into();
     }
+    // Best effort: targets without a place for hidden items rely on other annotations in the
+    // crate to record the environment variables, and go without replacement checks.
+    hidden.checks.extend(dependencies(&context));
+    target.place_hidden(hidden.checks, &mut trailer);
 
-    result.extend(input.into_token_stream());
+    let mut result = errors;
+    result.extend(target.into_token_stream());
+    result.extend(trailer);
 
     result.into()
 }
 
-struct BestBeforeArgs {
-    warning_date: NaiveDate,
-    expires_date: Option<NaiveDate>,
-    message: Option<String>,
-}
+/// Time-boxes a block or expression inside a function body.
+///
+/// Attribute macros cannot be put on statements, expressions or match arms on stable Rust, so
+/// temporary code in the middle of a function is wrapped in this macro instead. It takes the same
+/// parameters as [`macro@bestbefore`], followed by the code as the last argument, and evaluates to
+/// that code:
+///
+/// ```rust
+/// use bestbefore::bestbefore_block;
+///
+/// fn price(amount: u64) -> u64 {
+///     bestbefore_block!("2099-06", expires = "2099-09", message = "Remove the hot-fix", {
+///         if amount == 0 {
+///             return 1;
+///         }
+///     });
+///
+///     let discount = bestbefore_block!("2099-Q3", amount / 10);
+///     amount - discount
+/// }
+/// ```
+///
+/// Warnings are reported at the macro invocation, as there are no uses the deprecation could be
+/// reported at; `warn_at` has no effect. Once expired, the invocation fails to compile. The `note`
+/// stage has nothing to document and produces no output.
+///
+/// The macro cannot share the name `bestbefore` with the attribute, as Rust does not allow a
+/// crate to export an attribute and a function-like macro of the same name.
+#[proc_macro]
+pub fn bestbefore_block(input: TokenStream) -> TokenStream {
+    let BlockArgs { mut args, body } = parse_macro_input!(input as BlockArgs);
 
-impl Parse for BestBeforeArgs {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        let mut warning_date = None;
-        let mut expires_date = None;
-        let mut message = None;
-
-        if input.is_empty() {
-            return Err(syn::Error::new(
-                input.span(),
-                "Missing parameters. Expected either warning date or expires parameter",
-            ));
+    let context = match Context::from_env() {
+        Ok(context) => context,
+        Err(message) => {
+            return syn::Error::new(Span::call_site(), message)
+                .to_compile_error()
+                .into()
         }
+    };
+    let subject = subject("code block".to_string(), None, Span::call_site());
+    let outcome = match context.assess(&mut args, &subject) {
+        Ok(outcome) => outcome,
+        Err(err) => return err.to_compile_error().into(),
+    };
+    if let Err(err) = report(&context, &subject, &args, &outcome) {
+        return err.to_compile_error().into();
+    }
+    let Outcome {
+        verdict, details, ..
+    } = outcome;
+    let warning = match verdict {
+        Verdict::Fresh | Verdict::Note { .. } => TokenStream2::new(),
+        Verdict::Warn { message, .. } => definition_warning(None, &diagnostic(message, &details)),
+        Verdict::Fail { date, message } => {
+            return syn::Error::new(date.span, diagnostic(message, &details))
+                .to_compile_error()
+                .into();
+        }
+    };
+    let dependencies = dependencies(&context);
+    let replacement_check = args.replacement.as_ref().map(replacement_check);
+    // The statements of a plain block go straight into the generated one, as nesting the block
+    // would trip `unused_braces`.
+    let body = match body {
+        syn::Expr::Block(block) if block.label.is_none() && block.attrs.is_empty() => {
+            let stmts = block.block.stmts;
+            quote! { #(#stmts)* }
+        }
+        body => body.into_token_stream(),
+    };
 
-        if input.peek(LitStr) {
-            let date_lit: LitStr = input.parse()?;
-            warning_date = Some(parse_date(&date_lit.value()));
-
-            if !input.is_empty() {
-                input.parse::<Token![,]>()?;
-            }
+    quote! {
+        {
+            #dependencies
+            #replacement_check
+            #warning
+            #body
         }
+    }
+    .into()
+}
 
-        while !input.is_empty() {
-            let name: syn::Ident = input.parse()?;
-            input.parse::<Token![=]>()?;
+/// Evaluates `args` for `target` and applies the outcome.
+///
+/// Attributes are added to the target itself, items that produce definition-site warnings are
+/// added to `hidden`, along with a reference to the replacement. A failing verdict is returned as
+/// an error.
+fn apply(
+    target: &mut Target,
+    args: &mut BestBeforeArgs,
+    subject: &Subject,
+    context: &Context,
+    hidden: &mut Hidden,
+) -> syn::Result<()> {
+    let outcome = context.assess(args, subject)?;
+    if let Some(replacement) = &args.replacement {
+        hidden.checks.extend(replacement_check(replacement));
+    }
+    let mut attrs: Vec<syn::Attribute> = Vec::new();
 
-            if name == "expires" {
-                let date_lit = input.parse::<LitStr>()?;
-                expires_date = Some(parse_date(&date_lit.value()));
-            } else if name == "message" {
-                let msg_lit = input.parse::<LitStr>()?;
-                message = Some(msg_lit.value());
+    report(context, subject, args, &outcome)?;
+
+    let Outcome {
+        verdict, details, ..
+    } = outcome;
+    match verdict {
+        Verdict::Fresh => {}
+        Verdict::Fail { date, message } => {
+            return Err(syn::Error::new(date.span, diagnostic(message, &details)));
+        }
+        Verdict::Warn { message, loud, .. } => {
+            let message = diagnostic(message, &details);
+            // `#[deprecated]` is rejected on trait impls and their items, so those can only
+            // warn at the definition.
+            let trait_impl = matches!(target, Target::Item(syn::Item::Impl(item_impl)) if item_impl.trait_.is_some());
+            if args.warn_at.at_use() && !trait_impl {
+                attrs.push(syn::parse_quote!(#[warn(deprecated)]));
+                if let Target::Item(syn::Item::Impl(item_impl)) = target {
+                    deprecate_impl_items(item_impl, &message);
+                } else {
+                    attrs.push(syn::parse_quote!(#[deprecated(note = #message)]));
+                }
+            }
+            if args.warn_at.at_definition() || trait_impl {
+                hidden
+                    .warnings
+                    .extend(definition_warning(target.ident(), &message));
+            } else if loud || target.in_impl() {
+                // Demoted errors must not go unnoticed if the code is unused, but are not worth
+                // failing the expansion for where there is no place for the warning. Items of
+                // trait impls ignore `#[deprecated]`, and an impl item cannot tell which kind of
+                // impl it is in, so impl items always warn at the definition as well.
+                hidden
+                    .checks
+                    .extend(definition_warning(target.ident(), &message));
+            }
+        }
+        Verdict::Note { message } => {
+            // The mode and the clock are left out, as they are only relevant while compiling.
+            let references = details
+                .iter()
+                .filter(|(label, _)| *label != "mode" && *label != "clock")
+                .map(|(label, value)| format!("{}: {}", label, value))
+                .collect::<Vec<_>>();
+            let note = if references.is_empty() {
+                format!(" **Best before:** {}", message)
             } else {
-                return Err(syn::Error::new(
-                    name.span(),
-                    "Unknown parameter, expected 'expires' or 'message'",
-                ));
+                format!(" **Best before:** {} ({})", message, references.join(", "))
+            };
+            let documented = target
+                .attrs_mut()
+                .is_some_and(|attrs| attrs.iter().any(|attr| attr.path().is_ident("doc")));
+            if documented {
+                attrs.push(syn::parse_quote!(#[doc = ""]));
             }
+            attrs.push(syn::parse_quote!(#[doc = #note]));
+        }
+    }
 
-            if !input.is_empty() {
-                input.parse::<Token![,]>()?;
+    if !attrs.is_empty() {
+        match target.attrs_mut() {
+            Some(target_attrs) => {
+                // After the item's own docs, so that they still open with its summary.
+                let docs_end = target_attrs
+                    .iter()
+                    .rposition(|attr| attr.path().is_ident("doc"))
+                    .map_or(0, |index| index + 1);
+                target_attrs.splice(docs_end..docs_end, attrs);
+            }
+            None => {
+                return Err(syn::Error::new(
+                    Span::call_site(),
+                    format!("#[bestbefore] cannot add attributes to {}", subject.name),
+                ))
             }
         }
+    }
+    Ok(())
+}
 
-        // If warning_date is not provided but expires_date is, use expires_date as the warning_date
-        // This simplifies using #[bestbefore(expires="01.2028")] format
-        if warning_date.is_none() {
-            if let Some(exp_date) = expires_date {
-                warning_date = Some(exp_date);
-            } else {
-                return Err(syn::Error::new(input.span(),
-                    "Missing parameters. You must provide either a warning date or an expires parameter"));
+/// Evaluates the `#[bestbefore(...)]` annotations on the fields and variants of `target`.
+///
+/// Rust does not allow attribute macros on fields and variants, so these annotations are inert
+/// until the enclosing struct or enum is expanded. Errors of all members are combined.
+fn expand_members(target: &mut Target, context: &Context, hidden: &mut Hidden) -> syn::Result<()> {
+    let mut errors: Option<syn::Error> = None;
+    let mut collect = |result: syn::Result<()>| {
+        if let Err(err) = result {
+            match &mut errors {
+                Some(errors) => errors.combine(err),
+                None => errors = Some(err),
             }
         }
+    };
 
-        Ok(BestBeforeArgs {
-            warning_date: warning_date.unwrap(),
-            expires_date,
-            message,
-        })
+    match target {
+        Target::Item(syn::Item::Struct(item)) => {
+            let container = item.ident.to_string();
+            for (index, field) in item.fields.iter_mut().enumerate() {
+                collect(expand_field(field, &container, index, context, hidden));
+            }
+        }
+        Target::Item(syn::Item::Union(item)) => {
+            let container = item.ident.to_string();
+            for (index, field) in item.fields.named.iter_mut().enumerate() {
+                collect(expand_field(field, &container, index, context, hidden));
+            }
+        }
+        Target::Item(syn::Item::Enum(item)) => {
+            for variant in &mut item.variants {
+                let container = format!("{}::{}", item.ident, variant.ident);
+                for (index, field) in variant.fields.iter_mut().enumerate() {
+                    collect(expand_field(field, &container, index, context, hidden));
+                }
+                let annotations = take_annotations(&mut variant.attrs);
+                if annotations.is_empty() {
+                    continue;
+                }
+                let mut member = Target::Variant(variant.clone());
+                for annotation in annotations {
+                    let subject = subject(
+                        format!("variant {}", container),
+                        Some(container.clone()),
+                        annotation.span(),
+                    );
+                    collect(
+                        annotation
+                            .parse_args::<BestBeforeArgs>()
+                            .and_then(|mut args| {
+                                apply(&mut member, &mut args, &subject, context, hidden)
+                            }),
+                    );
+                }
+                if let Target::Variant(expanded) = member {
+                    *variant = expanded;
+                }
+            }
+        }
+        _ => {}
     }
+
+    errors.map_or(Ok(()), Err)
 }
 
-fn parse_date(date_str: &str) -> NaiveDate {
-    let parts: Vec<&str> = date_str.split('.').collect();
-    if parts.len() != 2 {
-        panic!(
-            "Invalid date format: '{}'. Expected format: 'MM.YYYY'",
-            date_str
+fn expand_field(
+    field: &mut syn::Field,
+    container: &str,
+    index: usize,
+    context: &Context,
+    hidden: &mut Hidden,
+) -> syn::Result<()> {
+    let annotations = take_annotations(&mut field.attrs);
+    if annotations.is_empty() {
+        return Ok(());
+    }
+    let path = match &field.ident {
+        Some(ident) => format!("{}::{}", container, ident),
+        None => format!("{}::{}", container, index),
+    };
+    let mut member = Target::Field(field.clone());
+    for annotation in annotations {
+        let subject = subject(
+            format!("field {}", path),
+            Some(path.clone()),
+            annotation.span(),
         );
+        let mut args = annotation.parse_args::<BestBeforeArgs>()?;
+        apply(&mut member, &mut args, &subject, context, hidden)?;
     }
+    if let Target::Field(expanded) = member {
+        *field = expanded;
+    }
+    Ok(())
+}
+
+/// Removes and returns the `#[bestbefore(...)]` attributes from `attrs`.
+fn take_annotations(attrs: &mut Vec<syn::Attribute>) -> Vec<syn::Attribute> {
+    let (annotations, rest) = std::mem::take(attrs)
+        .into_iter()
+        .partition(|attr| attr.path().is_ident("bestbefore"));
+    *attrs = rest;
+    annotations
+}
+
+/// Deprecates every method, associated const and associated type of an inherent impl block.
+///
+/// rustc does not reliably apply `#[deprecated]` on an impl block to its items, so the
+/// attribute is put on each item instead. Items that are already deprecated keep their own note.
+fn deprecate_impl_items(item_impl: &mut syn::ItemImpl, message: &str) {
+    for impl_item in &mut item_impl.items {
+        let attrs = match impl_item {
+            syn::ImplItem::Fn(item) => &mut item.attrs,
+            syn::ImplItem::Const(item) => &mut item.attrs,
+            syn::ImplItem::Type(item) => &mut item.attrs,
+            _ => continue,
+        };
+        if !attrs.iter().any(|attr| attr.path().is_ident("deprecated")) {
+            attrs.push(syn::parse_quote!(#[deprecated(note = #message)]));
+        }
+    }
+}
 
-    let month = parts[0].parse::<u32>().unwrap_or_else(|_| {
-        panic!("Invalid month: '{}'. Expected a number from 1-12", parts[0]);
+/// Items to be compiled along with the annotated code, without being visible outside of it.
+#[derive(Default)]
+struct Hidden {
+    /// Definition-site warnings, which fail the expansion if the target has no place for them.
+    warnings: TokenStream2,
+    /// Checks that are only placed where the target allows it.
+    checks: TokenStream2,
+}
+
+/// A reference to the replacement of the annotated code, so the build fails once the replacement
+/// is renamed or removed.
+fn replacement_check(path: &syn::Path) -> TokenStream2 {
+    // Associated items and enum variants with fields cannot be imported or referred to as values
+    // in general, so only the type they belong to is checked. They are recognized by the type
+    // before them. Imports take no generic arguments.
+    let associated = path.segments.iter().rev().nth(1).is_some_and(|segment| {
+        segment
+            .ident
+            .to_string()
+            .starts_with(|c: char| c.is_uppercase())
     });
+    let importable = path.segments.len() - usize::from(associated);
+    let leading_colon = path.leading_colon;
+    let idents = path
+        .segments
+        .iter()
+        .take(importable)
+        .map(|segment| &segment.ident);
+    quote_spanned! {path.span()=>
+        const _: () = {
+            #[allow(unused_imports)]
+            use #leading_colon #(#idents)::* as _;
+        };
+    }
+}
 
-    let year = parts[1].parse::<i32>().unwrap_or_else(|_| {
-        panic!("Invalid year: '{}'. Expected a valid year number", parts[1]);
+/// A hidden use of a deprecated marker, to be placed next to or inside the annotated code.
+///
+/// `#[deprecated]` only warns where the item is used, so unused items or glob-imported modules
+/// never warn. Using a deprecated marker named after the item makes the compiler warn at the item
+/// itself instead.
+fn definition_warning(ident: Option<syn::Ident>, message: &str) -> TokenStream2 {
+    let marker = ident.unwrap_or_else(|| syn::Ident::new("bestbefore", Span::call_site()));
+    quote! {
+        #[warn(deprecated)]
+        const _: () = {
+            #[deprecated(note = #message)]
+            #[allow(non_camel_case_types, dead_code)]
+            struct #marker;
+            let _ = #marker;
+        };
+    }
+}
+
+/// The location of `span`, if the compiler knows the file it comes from.
+fn location_of(span: Span) -> Option<Location> {
+    let span = span.unwrap();
+    let file = span.local_file()?;
+    let modules = std::fs::read_to_string(&file)
+        .map(|source| location::inline_modules(&source, span.line(), span.column()))
+        .unwrap_or_default();
+    Some(Location {
+        file: relative_to_package(&file),
+        line: span.line(),
+        column: span.column(),
+        modules,
+    })
+}
+
+/// Makes `file` relative to the package root.
+///
+/// The compiler reports files relative to its working directory, which is the workspace root
+/// when building with cargo.
+fn relative_to_package(file: &Path) -> PathBuf {
+    let absolute = match env::current_dir() {
+        Ok(dir) if file.is_relative() => dir.join(file),
+        _ => file.to_path_buf(),
+    };
+    env::var_os("CARGO_MANIFEST_DIR")
+        .and_then(|root| absolute.strip_prefix(root).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| file.to_path_buf())
+}
+
+/// Describes the code annotated at `span`, whose path ends in `path_name`.
+fn subject(name: String, path_name: Option<String>, span: Span) -> Subject {
+    let krate = env::var("CARGO_CRATE_NAME").ok();
+    let location = location_of(span);
+    let path = path_name.and_then(|path_name| {
+        location
+            .as_ref()
+            .map(|location| location::item_path(krate.as_deref(), location, &path_name))
     });
+    Subject {
+        name,
+        krate,
+        path,
+        location,
+    }
+}
+
+/// Appends the outcome for `args` to the report, if one is configured.
+fn report(
+    context: &Context,
+    subject: &Subject,
+    args: &BestBeforeArgs,
+    outcome: &Outcome,
+) -> syn::Result<()> {
+    let Some(path) = report::path(&context.config) else {
+        return Ok(());
+    };
+    let record = context.record(subject, args, outcome);
+    report::append(&path, &record.to_json())
+        .map_err(|message| syn::Error::new(Span::call_site(), message))
+}
 
-    if !(1..=12).contains(&month) {
-        panic!("Invalid month: {}. Expected a number from 1-12", month);
+/// Records the inputs of the expansion besides the source code, see [`tracking`].
+fn dependencies(context: &Context) -> TokenStream2 {
+    let mut dependencies = tracking::env_dependencies();
+    if let Some(path) = &context.config.path {
+        dependencies.extend(tracking::file_dependency(path));
     }
+    dependencies
+}
 
-    NaiveDate::from_ymd_opt(year, month, 1).unwrap_or_else(|| {
-        panic!("Invalid date: {}.{}", month, year);
+/// Appends `details` to `message`, one "label: value" line each.
+fn diagnostic(message: String, details: &[(&str, String)]) -> String {
+    details.iter().fold(message, |message, (label, value)| {
+        format!("{}\n{}: {}", message, label, value)
     })
 }
 
-fn item_name(item: &syn::Item) -> String {
-    match item {
-        syn::Item::Fn(item_fn) => item_fn.sig.ident.to_string(),
-        syn::Item::Mod(item_mod) => item_mod.ident.to_string(),
-        syn::Item::Impl(_) => "implementation block".to_string(),
-        syn::Item::Trait(item_trait) => format!("trait {}", item_trait.ident),
-        syn::Item::Struct(item_struct) => format!("struct {}", item_struct.ident),
-        syn::Item::Enum(item_enum) => format!("enum {}", item_enum.ident),
-        _ => "code block".to_string(),
+/// Keeps `#[bestbefore]` expansions up to date as days pass.
+///
+/// Cargo does not recompile a crate just because time has passed, so an unchanged crate keeps
+/// its old expansion, and a warning date that has passed in the meantime goes unnoticed until
+/// the crate is recompiled for another reason. Calling this macro from the crate's build script
+/// makes cargo re-run the build script, and therefore re-evaluate all annotations of the crate,
+/// on every build:
+///
+/// ```toml
+/// [build-dependencies]
+/// bestbefore = "0.1.0"
+/// ```
+///
+/// ```rust,ignore
+/// // build.rs
+/// fn main() {
+///     bestbefore::rerun_on_period_change!();
+/// }
+/// ```
+///
+/// The build script reports the environment variables read by `#[bestbefore]` to cargo. As
+/// cargo has no notion of time, the build script cannot tell it when a day has passed, so the
+/// crate is recompiled on every build; with incremental compilation this is usually cheap.
+///
+/// Independently of this helper, expansions record `BESTBEFORE_DATE`, `SOURCE_DATE_EPOCH`,
+/// `BESTBEFORE_TZ` and `CI` as inputs, so changing one of them always re-evaluates the
+/// annotations. With the `nightly` feature this is done through the compiler's tracked
+/// environment API instead of generated `option_env!` items.
+#[proc_macro]
+pub fn rerun_on_period_change(input: TokenStream) -> TokenStream {
+    if !input.is_empty() {
+        return syn::Error::new(
+            Span::call_site(),
+            "rerun_on_period_change!() does not take any arguments",
+        )
+        .to_compile_error()
+        .into();
+    }
+    tracking::build_script().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bestbefore_core::clock::Clock;
+    use bestbefore_core::config::Config;
+
+    /// Applies the annotation `args` to `target` as of `today`, returning the expanded target and
+    /// the items to be placed with it.
+    fn expand(args: &str, target: &str, today: &str) -> (String, Hidden) {
+        let context =
+            Context::new(Config::default(), Clock::fixed("test", today).unwrap()).unwrap();
+        let mut args: BestBeforeArgs = syn::parse_str(args).unwrap();
+        let mut target: Target = syn::parse_str(target).unwrap();
+        let subject = Subject {
+            name: target.name(),
+            krate: None,
+            path: None,
+            location: None,
+        };
+        let mut hidden = Hidden::default();
+        apply(&mut target, &mut args, &subject, &context, &mut hidden).unwrap();
+        (target.into_token_stream().to_string(), hidden)
+    }
+
+    #[test]
+    fn methods_warn_at_the_definition() {
+        // Either may be in a trait impl, where `#[deprecated]` has no effect.
+        let (target, hidden) = expand(r#""01.2020""#, "fn default() -> Self { Self }", "2024-01");
+        assert!(target.contains("deprecated"), "{}", target);
+        assert!(
+            hidden.checks.to_string().contains("struct default"),
+            "{}",
+            hidden.checks
+        );
+        let (_, hidden) = expand(r#""01.2020""#, "fn clone(&self) -> A { A }", "2024-01");
+        assert!(
+            hidden.checks.to_string().contains("struct clone"),
+            "{}",
+            hidden.checks
+        );
+    }
+
+    #[test]
+    fn free_functions_only_warn_at_uses() {
+        let (target, hidden) = expand(r#""01.2020""#, "fn legacy() -> u32 { 1 }", "2024-01");
+        assert!(target.contains("deprecated"), "{}", target);
+        assert!(hidden.warnings.is_empty() && hidden.checks.is_empty());
+    }
+
+    #[test]
+    fn trait_impls_only_warn_at_the_definition() {
+        let (target, hidden) = expand(
+            r#""01.2020""#,
+            "impl Default for Legacy { fn default() -> Self { Legacy } }",
+            "2024-01",
+        );
+        assert!(!target.contains("deprecated"), "{}", target);
+        assert!(!hidden.warnings.is_empty());
+    }
+
+    #[test]
+    fn replacements_are_imported() {
+        let import = |path: &str| {
+            let check = replacement_check(&syn::parse_str(path).unwrap()).to_string();
+            check[check.find("] use").unwrap() + 2..check.find("as _").unwrap()].to_string()
+        };
+        assert_eq!(import("crate::new_api"), "use crate :: new_api ");
+        assert_eq!(import("m::f::<u32>"), "use m :: f ");
+        // Of associated items and variants, only the type.
+        assert_eq!(import("crate::W::new"), "use crate :: W ");
+        assert_eq!(import("Vec::<u8>::new"), "use Vec ");
+        assert_eq!(import("::std::vec::Vec::new"), "use :: std :: vec :: Vec ");
+        assert_eq!(import("Shape::Circle"), "use Shape ");
+    }
+
+    #[test]
+    fn fresh_methods_do_not_warn() {
+        let (target, hidden) = expand(r#""01.2020""#, "fn default() -> Self { Self }", "2019-06");
+        assert!(!target.contains("deprecated"), "{}", target);
+        assert!(hidden.warnings.is_empty() && hidden.checks.is_empty());
     }
 }
@@ -0,0 +1,73 @@
+//! Making cargo re-run expansions when their inputs change.
+//!
+//! Cargo only recompiles a crate when one of its recorded inputs changed. The current day is
+//! not such an input, so without help an unchanged crate keeps its old expansion even after a
+//! date has passed. The environment variables read by the macro are recorded as inputs here, and
+//! [`build_script`] generates a build script that has cargo recompile the crate on every build.
+
+use bestbefore_core::environment::VARS;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use std::path::Path;
+
+/// Records the environment variables read by the expansion, [`VARS`], as dependencies of the
+/// crate being compiled.
+///
+/// With the `nightly` feature the variables are registered with the compiler directly. On
+/// stable this returns a module-level item using `option_env!`, which rustc tracks, so cargo
+/// recompiles the crate (and re-runs this macro) as soon as one of the variables changes.
+pub(crate) fn env_dependencies() -> TokenStream2 {
+    #[cfg(feature = "nightly")]
+    {
+        for name in VARS {
+            let _ = proc_macro::tracked_env::var(name);
+        }
+        TokenStream2::new()
+    }
+    #[cfg(not(feature = "nightly"))]
+    {
+        let vars = VARS;
+        let count = vars.len();
+        quote! {
+            const _: [::core::option::Option<&str>; #count] = [#(option_env!(#vars)),*];
+        }
+    }
+}
+
+/// Records the file at `path` as a dependency of the crate being compiled, so cargo recompiles
+/// the crate when the file changes.
+pub(crate) fn file_dependency(path: &Path) -> TokenStream2 {
+    let path = path.display().to_string();
+    quote! {
+        const _: &[u8] = include_bytes!(#path);
+    }
+}
+
+/// The body of a build script that makes cargo re-run expansions on every build.
+///
+/// Cargo re-runs a build script when a `rerun-if-changed` file is newer than the previous run,
+/// comparing modification times with each other and never with the clock. A file dated within
+/// the current period is never newer, and one dated at the next period is always newer, so no
+/// stamp can make cargo notice that a period has passed. The script therefore names a file that
+/// never exists, which cargo re-checks on every build. Each run recompiles the crate, whose
+/// expansions read the clock themselves, so nothing is passed to the compiler. The cost is a
+/// recompilation on every build, which is cheap with incremental compilation but not free, so
+/// the helper is opt-in per crate.
+pub(crate) fn build_script() -> TokenStream2 {
+    let vars = VARS;
+    quote! {
+        {
+            for variable in [#(#vars),*] {
+                println!("cargo:rerun-if-env-changed={}", variable);
+            }
+
+            let out_dir = ::std::env::var_os("OUT_DIR").expect(
+                "bestbefore::rerun_on_period_change!() must be called from a build script",
+            );
+            println!(
+                "cargo:rerun-if-changed={}",
+                ::std::path::Path::new(&out_dir).join("bestbefore.rerun").display()
+            );
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! The block form of `bestbefore_block!` compiles without warnings as long as its dates are
+//! ahead, so crates denying warnings only fail once code is due.
+
+#![deny(warnings)]
+
+use bestbefore::bestbefore_block;
+
+#[test]
+fn block_bodies_compile_without_warnings() {
+    let amount = 1;
+    let x = bestbefore_block!("2099-06", { amount + 1 });
+    let y = bestbefore_block!("2099-06", {
+        let doubled = amount * 2;
+        doubled + 1
+    });
+    assert_eq!((x, y), (2, 3));
+}
+
+#[test]
+fn block_statements_compile_without_warnings() {
+    let mut total = 0;
+    bestbefore_block!("2099-06", {
+        total += 1;
+    });
+    bestbefore_block!(expires = "2099-06", total += 1);
+    assert_eq!(total, 2);
+}